@@ -0,0 +1,143 @@
+use fastrand::Rng;
+use log::info;
+use serde::{Deserialize, Serialize};
+use crate::traffic::TrafficMatrix;
+
+/// Mix of flows that valid frames are sent in, weights of kinds being relative.
+///
+/// Frames of concurrent flows interleave, while frames of one flow keep their order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Flows {
+    /// Weight of single frames without any answer
+    pub single: f64,
+    /// Weight of requests answered by one response
+    pub request_response: f64,
+    /// Weight of TCP-like bursts of data acknowledged in the reverse direction
+    pub burst: f64,
+    /// Weight of long-lived sessions with frames in both directions
+    pub session: f64,
+    /// Mean count of data frames in a burst
+    pub burst_len: usize,
+    /// Count of data frames acknowledged by one frame in a burst
+    pub ack_every: usize,
+    /// Mean count of frames in a session
+    pub session_len: usize,
+    /// Count of flows in progress at once
+    pub concurrency: usize,
+}
+
+impl Default for Flows {
+    fn default() -> Self {
+        Flows {
+            single: 1.0,
+            request_response: 0.0,
+            burst: 0.0,
+            session: 0.0,
+            burst_len: 8,
+            ack_every: 2,
+            session_len: 100,
+            concurrency: 64,
+        }
+    }
+}
+
+/// Progress of a flow between two stations, by index in the address pool.
+enum Flow {
+    Single { src: usize, dst: usize, left: bool },
+    RequestResponse { src: usize, dst: usize, left: usize },
+    Burst { src: usize, dst: usize, data_left: usize, unacked: usize, ack_every: usize },
+    Session { a: usize, b: usize, left: usize },
+}
+
+impl Flow {
+    /// Next frame of the flow as source and destination, or `None` if it is over.
+    fn next(&mut self, rng: &mut Rng) -> Option<(usize, usize)> {
+        match self {
+            Flow::Single { src, dst, left } => {
+                std::mem::take(left).then_some((*src, *dst))
+            }
+            Flow::RequestResponse { src, dst, left } => {
+                *left = left.checked_sub(1)?;
+                Some(if *left == 1 { (*src, *dst) } else { (*dst, *src) })
+            }
+            Flow::Burst { src, dst, data_left, unacked, ack_every } => {
+                if *unacked >= *ack_every || (*data_left == 0 && *unacked > 0) {
+                    *unacked = 0;
+                    Some((*dst, *src))
+                } else {
+                    *data_left = data_left.checked_sub(1)?;
+                    *unacked += 1;
+                    Some((*src, *dst))
+                }
+            }
+            Flow::Session { a, b, left } => {
+                *left = left.checked_sub(1)?;
+                Some(if rng.bool() { (*a, *b) } else { (*b, *a) })
+            }
+        }
+    }
+}
+
+/// Generator of interleaved flows, picking stations with a traffic matrix.
+pub struct FlowGen<'a> {
+    flows: Flows,
+    matrix: &'a TrafficMatrix,
+    active: Vec<Flow>,
+    counts: [usize; 4],
+}
+
+impl<'a> FlowGen<'a> {
+    pub fn new(flows: &Flows, matrix: &'a TrafficMatrix) -> Self {
+        FlowGen { flows: flows.clone(), matrix, active: Vec::new(), counts: [0; 4] }
+    }
+
+    /// Start a flow of a kind chosen by weights.
+    fn start(&mut self, rng: &mut Rng) -> Flow {
+        let f = &self.flows;
+        let src = self.matrix.pick_src(rng);
+        let dst = self.matrix.pick_dst(rng, src);
+        let weights = [f.single, f.request_response, f.burst, f.session];
+        let mut x = rng.f64() * weights.iter().sum::<f64>();
+        let kind = weights.iter().position(|w| {
+            x -= w;
+            x < 0.0
+        }).unwrap_or(0);
+        self.counts[kind] += 1;
+        match kind {
+            1 => Flow::RequestResponse { src, dst, left: 2 },
+            2 => Flow::Burst {
+                src,
+                dst,
+                data_left: rng.usize(1..f.burst_len.max(1) * 2),
+                unacked: 0,
+                ack_every: f.ack_every.max(1),
+            },
+            3 => Flow::Session { a: src, b: dst, left: rng.usize(1..f.session_len.max(1) * 2) },
+            _ => Flow::Single { src, dst, left: true },
+        }
+    }
+
+    /// Next frame as source and destination, from one of flows in progress.
+    pub fn next(&mut self, rng: &mut Rng) -> (usize, usize) {
+        loop {
+            while self.active.len() < self.flows.concurrency.max(1) {
+                let flow = self.start(rng);
+                self.active.push(flow);
+            }
+            let i = rng.usize(0..self.active.len());
+            match self.active[i].next(rng) {
+                Some(pair) => return pair,
+                None => {
+                    self.active.swap_remove(i);
+                }
+            }
+        }
+    }
+
+    /// Log the count of flows started of each kind.
+    pub fn report(&self) {
+        info!("Started {} single frames, {} request/response pairs, {} bursts and {} sessions.",
+            self.counts[0], self.counts[1], self.counts[2], self.counts[3]);
+    }
+}
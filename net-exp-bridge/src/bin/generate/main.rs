@@ -0,0 +1,521 @@
+mod flow;
+mod scenario;
+mod traffic;
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::fs::File;
+use std::path::PathBuf;
+use clap::Parser;
+use fastrand::Rng;
+use net_exp_bridge::{trace, Address, Frame, FrameData, Membership, Metadata, Migration, Segment};
+use net_exp_bridge::stream::FrameWriter;
+use std::io::{BufWriter, Write};
+use log::info;
+use crate::flow::FlowGen;
+use crate::scenario::Scenario;
+use crate::traffic::{Traffic, TrafficMatrix};
+
+/// Generate addresses, segments and frames for the simulation of bridge.
+///
+/// Options given on command line override those of the scenario file.
+#[derive(Parser)]
+struct Args {
+    /// Scenario file in TOML, or JSON if ending with `.json`
+    #[arg(long)]
+    scenario: Option<PathBuf>,
+    /// Seed of all randomness, to reproduce a dataset (random if not specified)
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Count of valid addresses
+    #[arg(long)]
+    valid_addr_cnt: Option<usize>,
+    /// Count of invalid addresses
+    #[arg(long)]
+    invalid_addr_cnt: Option<usize>,
+    /// Count of segments
+    #[arg(long)]
+    seg_cnt: Option<usize>,
+    /// Count of valid frames
+    #[arg(long)]
+    valid_frame_cnt: Option<usize>,
+    /// Count of invalid frames
+    #[arg(long)]
+    invalid_frame_cnt: Option<usize>,
+    /// Count of station migrations
+    #[arg(long)]
+    migration_cnt: Option<usize>,
+    /// Count of frames to the broadcast address
+    #[arg(long)]
+    broadcast_frame_cnt: Option<usize>,
+    /// Count of frames to multicast groups
+    #[arg(long)]
+    multicast_frame_cnt: Option<usize>,
+    /// Count of multicast groups
+    #[arg(long)]
+    group_cnt: Option<usize>,
+    /// Traffic matrix with default parameters (uniform, zipf, hotspot, client_server)
+    #[arg(long, value_parser = |s: &str| s.parse::<Traffic>())]
+    traffic: Option<Traffic>,
+    /// Share of valid frames between stations of the same segment
+    #[arg(long)]
+    intra_segment_ratio: Option<f64>,
+    /// Write frames as a text trace `frame.txt` too
+    #[arg(long)]
+    frame_trace: bool,
+    /// Directory to write output files in
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+    /// Prefix of names of output files
+    #[arg(long)]
+    prefix: Option<String>,
+}
+
+impl Args {
+    /// Resolve the scenario from file and command line.
+    fn scenario(self) -> Scenario {
+        let mut scenario = self.scenario.as_deref().map(Scenario::load).unwrap_or_default();
+        scenario.seed = self.seed.or(scenario.seed);
+        scenario.valid_addr_cnt = self.valid_addr_cnt.unwrap_or(scenario.valid_addr_cnt);
+        scenario.invalid_addr_cnt = self.invalid_addr_cnt.unwrap_or(scenario.invalid_addr_cnt);
+        scenario.seg_cnt = self.seg_cnt.unwrap_or(scenario.seg_cnt);
+        scenario.valid_frame_cnt = self.valid_frame_cnt.unwrap_or(scenario.valid_frame_cnt);
+        scenario.invalid_frame_cnt = self.invalid_frame_cnt.unwrap_or(scenario.invalid_frame_cnt);
+        scenario.migration_cnt = self.migration_cnt.unwrap_or(scenario.migration_cnt);
+        scenario.broadcast_frame_cnt = self.broadcast_frame_cnt.unwrap_or(scenario.broadcast_frame_cnt);
+        scenario.multicast_frame_cnt = self.multicast_frame_cnt.unwrap_or(scenario.multicast_frame_cnt);
+        scenario.group_cnt = self.group_cnt.unwrap_or(scenario.group_cnt);
+        scenario.traffic = self.traffic.unwrap_or(scenario.traffic);
+        scenario.intra_segment_ratio = self.intra_segment_ratio.or(scenario.intra_segment_ratio);
+        scenario.frame_trace |= self.frame_trace;
+        scenario.out_dir = self.out_dir.unwrap_or(scenario.out_dir);
+        scenario.prefix = self.prefix.unwrap_or(scenario.prefix);
+        scenario
+    }
+}
+
+/// Generate random byte array of specified size with `fastrand` API.
+fn gen_byte_arr<const N: usize>(rng: &mut Rng) -> [u8; N] {
+    let mut data = [0u8; N];
+    rng.fill(&mut data);
+    data
+}
+
+/// Generate a physical address of a single station.
+fn gen_addr(rng: &mut Rng) -> Address {
+    let mut data = gen_byte_arr::<6>(rng);
+    data[0] &= !0x01;
+    Address { data }
+}
+
+/// Generate a pool of physical addresses, unique, in order of generation.
+fn gen_addr_pool(rng: &mut Rng, count: usize) -> Vec<Address> {
+    gen_invalid_addr_pool(rng, &[], count)
+}
+
+/// Generate a pool of invalid addresses, unique and not clashing with valid ones.
+fn gen_invalid_addr_pool(rng: &mut Rng, addr_pool: &[Address], count: usize) -> Vec<Address> {
+    let mut unique_set: HashSet<Address> = HashSet::from_iter(addr_pool.iter().copied());
+    let mut pool = Vec::with_capacity(count);
+    while pool.len() < count {
+        let addr = gen_addr(rng);
+        if unique_set.insert(addr) {
+            pool.push(addr);
+        }
+    }
+    pool
+}
+
+/// Generate a pool of multicast group addresses, unique, mapped from IPv4 groups.
+fn gen_group_pool(rng: &mut Rng, count: usize) -> Vec<Address> {
+    let mut unique_set: HashSet<Address> = HashSet::with_capacity(count);
+    let mut pool = Vec::with_capacity(count);
+    while pool.len() < count {
+        let [a, b, c] = gen_byte_arr(rng);
+        let group = Address { data: [0x01, 0x00, 0x5e, a & 0x7f, b, c] };
+        if unique_set.insert(group) {
+            pool.push(group);
+        }
+    }
+    pool
+}
+
+/// Generate a segment.
+fn gen_seg(rng: &mut Rng) -> Segment {
+    Segment { data: gen_byte_arr(rng) }
+}
+
+/// Generate a pool of segments, unique, in order of generation.
+fn gen_seg_pool(rng: &mut Rng, count: usize) -> Vec<Segment> {
+    let mut unique_set: HashSet<Segment> = HashSet::with_capacity(count);
+    let mut pool = Vec::with_capacity(count);
+    while pool.len() < count {
+        let seg = gen_seg(rng);
+        if unique_set.insert(seg) {
+            pool.push(seg);
+        }
+    }
+    pool
+}
+
+/// Generate frame data.
+fn gen_data(rng: &mut Rng) -> FrameData {
+    gen_byte_arr(rng)
+}
+
+/// Generate frame from a source to a destination.
+///
+/// The frame comes from the segment where its source is located.
+fn gen_frame(rng: &mut Rng, src: Address, dst: Address, location: &HashMap<Address, Segment>) -> Frame {
+    let src_seg = location[&src];
+    let data = gen_data(rng);
+    Frame { src, src_seg, dst, data, vlan: None }
+}
+
+/// Generate a sequence of frames between addresses of the pool, sent in flows.
+fn gen_frame_seq<'a, 'm>(mut rng: Rng, flow_gen: &'a mut FlowGen<'m>, addr_pool: &'a [Address],
+                         location: &'a HashMap<Address, Segment>, count: usize)
+                         -> impl ExactSizeIterator<Item = Frame> + use<'a, 'm> {
+    (0..count).map(move |_| {
+        let (src, dst) = flow_gen.next(&mut rng);
+        gen_frame(&mut rng, addr_pool[src], addr_pool[dst], location)
+    })
+}
+
+/// Generate a sequence of frames from addresses of the pool to invalid addresses.
+fn gen_inv_frame_seq<'a>(mut rng: Rng, matrix: &'a TrafficMatrix, addr_pool: &'a [Address],
+                         location: &'a HashMap<Address, Segment>, inv_addr_pool: &'a [Address],
+                         count: usize) -> impl ExactSizeIterator<Item = Frame> + 'a {
+    (0..count).map(move |_| {
+        let src = addr_pool[matrix.pick_src(&mut rng)];
+        let dst = inv_addr_pool[rng.usize(0..inv_addr_pool.len())];
+        gen_frame(&mut rng, src, dst, location)
+    })
+}
+
+/// Generate a sequence of frames from addresses of the pool to the broadcast address and to
+/// multicast groups, in random order.
+fn gen_group_frame_seq<'a>(mut rng: Rng, matrix: &'a TrafficMatrix, addr_pool: &'a [Address],
+                           location: &'a HashMap<Address, Segment>, group_pool: &'a [Address],
+                           broadcast_cnt: usize, multicast_cnt: usize)
+                           -> impl ExactSizeIterator<Item = Frame> + 'a {
+    let mut broadcast_rng = Rng::with_seed(rng.u64(..));
+    let broadcast_seq = (0..broadcast_cnt).map(move |_| {
+        let src = addr_pool[matrix.pick_src(&mut broadcast_rng)];
+        gen_frame(&mut broadcast_rng, src, Address::BROADCAST, location)
+    });
+    // no groups, no frames to them
+    let multicast_cnt = if group_pool.is_empty() { 0 } else { multicast_cnt };
+    let mut multicast_rng = Rng::with_seed(rng.u64(..));
+    let multicast_seq = (0..multicast_cnt).map(move |_| {
+        let src = addr_pool[matrix.pick_src(&mut multicast_rng)];
+        let group = group_pool[multicast_rng.usize(0..group_pool.len())];
+        gen_frame(&mut multicast_rng, src, group, location)
+    });
+    interleave(rng, broadcast_seq, multicast_seq)
+}
+
+/// Merge of two sequences of frames at random, keeping the order within each of them.
+struct Interleave<A, B> {
+    rng: Rng,
+    seq: A,
+    other: B,
+}
+
+impl<A: ExactSizeIterator<Item = Frame>, B: ExactSizeIterator<Item = Frame>> Iterator for Interleave<A, B> {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        let left = self.seq.len() + self.other.len();
+        if left == 0 {
+            return None;
+        }
+        if self.rng.usize(0..left) < self.seq.len() {
+            self.seq.next()
+        } else {
+            self.other.next()
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.seq.len() + self.other.len();
+        (left, Some(left))
+    }
+}
+
+impl<A: ExactSizeIterator<Item = Frame>, B: ExactSizeIterator<Item = Frame>> ExactSizeIterator for Interleave<A, B> {}
+
+/// Merge two sequences of frames at random, keeping the order within each of them.
+fn interleave<A, B>(rng: Rng, seq: A, other: B) -> Interleave<A, B> {
+    Interleave { rng, seq, other }
+}
+
+/// Generate a mapping from address to segment from their pools.
+fn gen_addr_seg(rng: &mut Rng, addr_pool: Vec<Address>, seg_pool: &[Segment]) -> Vec<(Address, Segment)> {
+    let mut seq = Vec::with_capacity(addr_pool.len() * seg_pool.len());
+    let least = addr_pool.len() / seg_pool.len();
+    // assign segment for addresses
+    for (i, seg) in seg_pool.iter().enumerate() {
+        let begin = i * least;
+        for j in 0..least {
+            seq.push((addr_pool[begin + j], *seg));
+        }
+    }
+    // treat remaining ones
+    if seq.len() < addr_pool.len() {
+        let begin = seq.len();
+        for addr in addr_pool.iter().skip(begin) {
+            seq.push((*addr, seg_pool[rng.usize(0..seg_pool.len())]));
+        }
+    }
+    seq
+}
+
+/// Generate migrations of stations to other segments, scheduled over a sequence of frames.
+fn gen_migrations(rng: &mut Rng, addr_seg_seq: &[(Address, Segment)], seg_pool: &[Segment],
+                  frame_cnt: usize, count: usize) -> Vec<Migration> {
+    let mut location = HashMap::<Address, Segment>::from_iter(addr_seg_seq.iter().copied());
+    let mut indices = (0..count).map(|_| rng.usize(0..frame_cnt.max(1))).collect::<Vec<_>>();
+    indices.sort_unstable();
+    let mut seq = Vec::with_capacity(count);
+    for index in indices {
+        let addr = addr_seg_seq[rng.usize(0..addr_seg_seq.len())].0;
+        let cur = location[&addr];
+        let mut seg = cur;
+        while seg == cur {
+            seg = seg_pool[rng.usize(0..seg_pool.len())];
+        }
+        location.insert(addr, seg);
+        seq.push(Migration { index, addr, seg });
+    }
+    seq
+}
+
+/// Generate memberships of stations in groups, some joining from the beginning and others joining
+/// or leaving over a sequence of frames, each reported from the segment of the station then.
+fn gen_memberships(rng: &mut Rng, addr_seg_seq: &[(Address, Segment)], migrations: &[Migration],
+                   group_pool: &[Address], group_size: usize, frame_cnt: usize,
+                   count: usize) -> Vec<Membership> {
+    // ordered, so that the member leaving follows from the seed alone
+    let mut members = vec![BTreeSet::<Address>::new(); group_pool.len()];
+    let mut changes = Vec::new();
+    for (i, group) in group_pool.iter().enumerate() {
+        for _ in 0..group_size.min(addr_seg_seq.len()) {
+            let addr = addr_seg_seq[rng.usize(0..addr_seg_seq.len())].0;
+            if members[i].insert(addr) {
+                changes.push((0, addr, *group, true));
+            }
+        }
+    }
+    let count = if group_pool.is_empty() { 0 } else { count };
+    let mut indices = (0..count).map(|_| rng.usize(0..frame_cnt.max(1))).collect::<Vec<_>>();
+    indices.sort_unstable();
+    for index in indices {
+        let i = rng.usize(0..group_pool.len());
+        let addr = if rng.bool() && !members[i].is_empty() {
+            // a member leaves
+            *members[i].iter().nth(rng.usize(0..members[i].len())).unwrap()
+        } else {
+            addr_seg_seq[rng.usize(0..addr_seg_seq.len())].0
+        };
+        let join = !members[i].remove(&addr);
+        if join {
+            members[i].insert(addr);
+        }
+        changes.push((index, addr, group_pool[i], join));
+    }
+    // report from where the station is at the time
+    let mut location = HashMap::<Address, Segment>::from_iter(addr_seg_seq.iter().copied());
+    let mut migrations = migrations.iter().peekable();
+    changes.into_iter()
+        .map(|(index, addr, group, join)| {
+            while let Some(migration) = migrations.next_if(|m| m.index <= index) {
+                location.insert(migration.addr, migration.seg);
+            }
+            Membership { index, addr, group, seg: location[&addr], join }
+        })
+        .collect()
+}
+
+/// Correct source segments of frames following the migrations of their sources, counting the
+/// frames whose destination is on the segment of the source at the time.
+fn relocate<'a>(frame_seq: impl Iterator<Item = Frame> + 'a, addr_seg_seq: &[(Address, Segment)],
+                migrations: &'a [Migration], intra: &'a mut usize) -> impl Iterator<Item = Frame> + 'a {
+    let mut location = HashMap::<Address, Segment>::from_iter(addr_seg_seq.iter().copied());
+    let mut migrations = migrations.iter().peekable();
+    frame_seq.enumerate().map(move |(i, mut frame)| {
+        while let Some(migration) = migrations.next_if(|m| m.index <= i) {
+            location.insert(migration.addr, migration.seg);
+        }
+        frame.src_seg = location[&frame.src];
+        if location.get(&frame.dst) == Some(&frame.src_seg) {
+            *intra += 1;
+        }
+        frame
+    })
+}
+
+/// Write frames one by one as they are generated, to a frame file for use with simulation and to a
+/// text trace if the scenario tells. Returns the count of frames.
+fn serialize_frames(scenario: &Scenario, frame_seq: impl Iterator<Item = Frame>) -> u64 {
+    let frame_bin = BufWriter::new(File::create(scenario.path("frame.bin")).unwrap());
+    let mut frame_bw = FrameWriter::new(frame_bin).unwrap();
+    let mut trace_bw = scenario.frame_trace.then(|| {
+        let mut trace_bw = BufWriter::new(File::create(scenario.path("frame.txt")).unwrap());
+        writeln!(trace_bw, "{}", trace::FRAME_HEADER).unwrap();
+        trace_bw
+    });
+    for frame in frame_seq {
+        frame_bw.write(&frame).unwrap();
+        if let Some(trace_bw) = &mut trace_bw {
+            writeln!(trace_bw, "{}", frame).unwrap();
+        }
+    }
+    let count = frame_bw.count();
+    frame_bw.finish().unwrap();
+    if let Some(mut trace_bw) = trace_bw {
+        trace_bw.flush().unwrap();
+    }
+    count
+}
+
+/// Serialize data for use with simulation binary & human analysis.
+fn serialize(scenario: &Scenario, meta: &Metadata, addr_seg_seq: &[(Address, Segment)], inv_addr_pool: &[Address],
+             migrations: &[Migration], memberships: &[Membership]) {
+    // encode binary format for use with simulation
+    let addr_seg_rmp = File::create(scenario.path("addr_seg.rmp")).unwrap();
+    let inv_addr_rmp = File::create(scenario.path("inv_addr.rmp")).unwrap();
+    let migration_rmp = File::create(scenario.path("migration.rmp")).unwrap();
+    let membership_rmp = File::create(scenario.path("membership.rmp")).unwrap();
+    let meta_rmp = File::create(scenario.path("meta.rmp")).unwrap();
+    rmp_serde::encode::write(&mut BufWriter::new(addr_seg_rmp), addr_seg_seq).unwrap();
+    rmp_serde::encode::write(&mut BufWriter::new(inv_addr_rmp), inv_addr_pool).unwrap();
+    rmp_serde::encode::write(&mut BufWriter::new(migration_rmp), migrations).unwrap();
+    rmp_serde::encode::write(&mut BufWriter::new(membership_rmp), memberships).unwrap();
+    rmp_serde::encode::write(&mut BufWriter::new(meta_rmp), meta).unwrap();
+
+    // encode text for human-based analysis
+    let addr_seg_file = File::create(scenario.path("addr_seg.txt")).unwrap();
+    let inv_addr_file = File::create(scenario.path("inv_addr.txt")).unwrap();
+    let migration_file = File::create(scenario.path("migration.txt")).unwrap();
+    let membership_file = File::create(scenario.path("membership.txt")).unwrap();
+    let meta_file = File::create(scenario.path("meta.txt")).unwrap();
+    let mut addr_seg_bw = BufWriter::new(addr_seg_file);
+    let mut inv_addr_bw = BufWriter::new(inv_addr_file);
+    let mut migration_bw = BufWriter::new(migration_file);
+    let mut membership_bw = BufWriter::new(membership_file);
+    for (addr, seg) in addr_seg_seq {
+        writeln!(addr_seg_bw, "{} {}", addr, seg).unwrap();
+    }
+    for addr in inv_addr_pool {
+        writeln!(inv_addr_bw, "{}", addr).unwrap();
+    }
+    for migration in migrations {
+        writeln!(migration_bw, "{}", migration).unwrap();
+    }
+    for membership in memberships {
+        writeln!(membership_bw, "{}", membership).unwrap();
+    }
+    write!(BufWriter::new(meta_file), "{}", meta).unwrap();
+}
+
+
+fn main() {
+    env_logger::init();
+    let scenario = Args::parse().scenario();
+    scenario.validate();
+    let meta = Metadata {
+        seed: scenario.seed.unwrap_or_else(|| fastrand::u64(..)),
+        valid_addr_cnt: scenario.valid_addr_cnt,
+        invalid_addr_cnt: scenario.invalid_addr_cnt,
+        seg_cnt: scenario.seg_cnt,
+        valid_frame_cnt: scenario.valid_frame_cnt,
+        invalid_frame_cnt: scenario.invalid_frame_cnt,
+        migration_cnt: scenario.migration_cnt,
+        broadcast_frame_cnt: scenario.broadcast_frame_cnt,
+        multicast_frame_cnt: scenario.multicast_frame_cnt,
+        group_cnt: scenario.group_cnt,
+        group_size: scenario.group_size,
+        membership_cnt: scenario.membership_cnt,
+        traffic: toml::Value::try_from(&scenario.traffic).unwrap().to_string(),
+        intra_segment_ratio: scenario.intra_segment_ratio,
+        flows: toml::Value::try_from(&scenario.flows).unwrap().to_string(),
+        frame_trace: scenario.frame_trace,
+    };
+    info!("Seed {}.", meta.seed);
+    let mut rng = Rng::with_seed(meta.seed);
+
+    // create pools
+    info!("Address pool...");
+    let addr_pool = gen_addr_pool(&mut rng, meta.valid_addr_cnt);
+    info!("Invalid address pool...");
+    let inv_addr_pool = gen_addr_pool(&mut rng, meta.invalid_addr_cnt);
+    info!("Segment pool...");
+    let seg_pool = gen_seg_pool(&mut rng, meta.seg_cnt);
+    info!("Group pool...");
+    let group_pool = gen_group_pool(&mut rng, meta.group_cnt);
+
+    // generate segment mapping
+    let addr_seg_seq = gen_addr_seg(&mut rng, addr_pool.clone(), &seg_pool);
+    let location = HashMap::<Address, Segment>::from_iter(addr_seg_seq.iter().copied());
+
+    // fabricate frames
+    let mut matrix = TrafficMatrix::new(&scenario.traffic, addr_pool.len());
+    if let Some(ratio) = scenario.intra_segment_ratio {
+        // the mapping follows the order of the address pool
+        let seg_of = addr_seg_seq.iter().map(|(_, seg)| seg_pool.iter().position(|s| s == seg).unwrap()).collect();
+        matrix = matrix.with_locality(ratio, seg_of);
+    }
+    // frames are generated as they are written, each kind with randomness of its own
+    let mut flow_gen = FlowGen::new(&scenario.flows, &matrix);
+    let frame_seq = gen_frame_seq(
+        Rng::with_seed(rng.u64(..)), &mut flow_gen, &addr_pool, &location, meta.valid_frame_cnt);
+    let inv_frame_seq = gen_inv_frame_seq(
+        Rng::with_seed(rng.u64(..)), &matrix, &addr_pool, &location, &inv_addr_pool, meta.invalid_frame_cnt);
+    let frame_seq = interleave(Rng::with_seed(rng.u64(..)), frame_seq, inv_frame_seq);
+    let group_frame_seq = gen_group_frame_seq(Rng::with_seed(rng.u64(..)), &matrix, &addr_pool, &location,
+                                              &group_pool, meta.broadcast_frame_cnt, meta.multicast_frame_cnt);
+    let frame_seq = interleave(Rng::with_seed(rng.u64(..)), frame_seq, group_frame_seq);
+    let frame_cnt = frame_seq.len();
+
+    // schedule station mobility
+    info!("Migrations...");
+    let migrations = gen_migrations(&mut rng, &addr_seg_seq, &seg_pool, frame_cnt, meta.migration_cnt);
+    info!("Memberships...");
+    let memberships = gen_memberships(&mut rng, &addr_seg_seq, &migrations, &group_pool, meta.group_size,
+                                      frame_cnt, meta.membership_cnt);
+
+    info!("Serialization...");
+    fs::create_dir_all(&scenario.out_dir).unwrap();
+    serialize(&scenario, &meta, &addr_seg_seq, &inv_addr_pool, &migrations, &memberships);
+    info!("Frame sequence...");
+    let mut intra = 0;
+    let frame_seq = relocate(frame_seq, &addr_seg_seq, &migrations, &mut intra);
+    let frame_cnt = serialize_frames(&scenario, frame_seq);
+    flow_gen.report();
+    info!("{} of {} frames between stations of the same segment.", intra, frame_cnt);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Memberships generated from a seed, with stations migrating meanwhile.
+    fn memberships(seed: u64) -> Vec<Membership> {
+        let mut rng = Rng::with_seed(seed);
+        let addr_pool = gen_addr_pool(&mut rng, 200);
+        let seg_pool = gen_seg_pool(&mut rng, 10);
+        let group_pool = gen_group_pool(&mut rng, 5);
+        let addr_seg_seq = gen_addr_seg(&mut rng, addr_pool, &seg_pool);
+        let migrations = gen_migrations(&mut rng, &addr_seg_seq, &seg_pool, 10_000, 50);
+        gen_memberships(&mut rng, &addr_seg_seq, &migrations, &group_pool, 20, 10_000, 500)
+    }
+
+    #[test]
+    fn memberships_follow_seed() {
+        let first = memberships(7);
+        assert!(first.iter().any(|m| !m.join));
+        assert_eq!(memberships(7), first);
+        assert_ne!(memberships(8), first);
+    }
+}
@@ -0,0 +1,101 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use crate::flow::Flows;
+use crate::traffic::Traffic;
+
+/// Scenario of generation, read from a TOML or JSON file.
+///
+/// Missing keys take their default values.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    /// Seed of all randomness (random if not specified)
+    pub seed: Option<u64>,
+    /// Count of valid addresses
+    pub valid_addr_cnt: usize,
+    /// Count of invalid addresses
+    pub invalid_addr_cnt: usize,
+    /// Count of segments
+    pub seg_cnt: usize,
+    /// Count of valid frames
+    pub valid_frame_cnt: usize,
+    /// Count of invalid frames
+    pub invalid_frame_cnt: usize,
+    /// Count of station migrations
+    pub migration_cnt: usize,
+    /// Count of frames to the broadcast address
+    pub broadcast_frame_cnt: usize,
+    /// Count of frames to multicast groups
+    pub multicast_frame_cnt: usize,
+    /// Count of multicast groups
+    pub group_cnt: usize,
+    /// Count of stations joining each group from the beginning
+    pub group_size: usize,
+    /// Count of stations joining or leaving groups later on
+    pub membership_cnt: usize,
+    /// Traffic matrix, i.e. who talks to whom
+    pub traffic: Traffic,
+    /// Share of valid frames between stations of the same segment (as the traffic matrix falls if not specified)
+    pub intra_segment_ratio: Option<f64>,
+    /// Mix of flows that valid frames are sent in
+    pub flows: Flows,
+    /// Whether to write frames as a text trace too
+    pub frame_trace: bool,
+    /// Directory to write output files in
+    pub out_dir: PathBuf,
+    /// Prefix of names of output files
+    pub prefix: String,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario {
+            seed: None,
+            valid_addr_cnt: 5000,
+            invalid_addr_cnt: 100,
+            seg_cnt: 100,
+            valid_frame_cnt: 1000_0000,
+            invalid_frame_cnt: 10_0000,
+            migration_cnt: 1000,
+            broadcast_frame_cnt: 0,
+            multicast_frame_cnt: 0,
+            group_cnt: 10,
+            group_size: 50,
+            membership_cnt: 100,
+            traffic: Traffic::Uniform,
+            intra_segment_ratio: None,
+            flows: Flows::default(),
+            frame_trace: false,
+            out_dir: PathBuf::from("."),
+            prefix: String::new(),
+        }
+    }
+}
+
+impl Scenario {
+    /// Load a scenario from file, as JSON if its extension is `.json` or TOML otherwise.
+    pub fn load(path: &Path) -> Self {
+        let text = fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("cannot read scenario {}: {}", path.display(), err));
+        if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&text)
+                .unwrap_or_else(|err| panic!("invalid scenario {}: {}", path.display(), err))
+        } else {
+            toml::from_str(&text)
+                .unwrap_or_else(|err| panic!("invalid scenario {}: {}", path.display(), err))
+        }
+    }
+
+    /// Check that the scenario can be generated, failing with the reason otherwise.
+    pub fn validate(&self) {
+        assert!(self.seg_cnt > 0, "invalid scenario: seg_cnt must be at least 1");
+        assert!(self.migration_cnt == 0 || self.seg_cnt > 1,
+                "invalid scenario: migrations need seg_cnt of at least 2");
+    }
+
+    /// Path of an output file of specified name.
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.out_dir.join(format!("{}{}", self.prefix, name))
+    }
+}
@@ -0,0 +1,232 @@
+use std::str::FromStr;
+use fastrand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Traffic matrix, i.e. how likely each pair of stations talks.
+///
+/// Stations are ranked by their order in the address pool, which is random.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum Traffic {
+    /// Every station equally likely to send and receive.
+    Uniform,
+    /// Popularity of sending and receiving decreasing with rank following Zipf's law.
+    Zipf(Zipf),
+    /// A few servers receiving most traffic, sources chosen evenly.
+    Hotspot(Hotspot),
+    /// Clients talking to servers and servers answering clients, never clients to each other.
+    ClientServer(ClientServer),
+}
+
+/// Parameters of `Traffic::Zipf`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Zipf {
+    /// Exponent of the law, more skewed when larger
+    pub exponent: f64,
+}
+
+impl Default for Zipf {
+    fn default() -> Self {
+        Zipf { exponent: 1.0 }
+    }
+}
+
+/// Parameters of `Traffic::Hotspot`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hotspot {
+    /// Count of servers
+    pub servers: usize,
+    /// Share of frames destined to servers
+    pub share: f64,
+}
+
+impl Default for Hotspot {
+    fn default() -> Self {
+        Hotspot { servers: 10, share: 0.8 }
+    }
+}
+
+/// Parameters of `Traffic::ClientServer`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientServer {
+    /// Count of servers, other stations being clients
+    pub servers: usize,
+    /// Share of frames sent by servers to clients
+    pub reply: f64,
+}
+
+impl Default for ClientServer {
+    fn default() -> Self {
+        ClientServer { servers: 50, reply: 0.5 }
+    }
+}
+
+impl FromStr for Traffic {
+    type Err = String;
+
+    /// Parse the name of a model with default parameters.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(Traffic::Uniform),
+            "zipf" => Ok(Traffic::Zipf(Zipf::default())),
+            "hotspot" => Ok(Traffic::Hotspot(Hotspot::default())),
+            "client_server" => Ok(Traffic::ClientServer(ClientServer::default())),
+            _ => Err(format!("unknown traffic model \"{}\", expected uniform, zipf, hotspot or client_server", s)),
+        }
+    }
+}
+
+/// Choice of an index with some weights.
+enum Sampler {
+    /// Every index in the range equally likely.
+    Range(usize, usize),
+    /// Index chosen with cumulative weights.
+    Weighted(Vec<f64>),
+}
+
+impl Sampler {
+    fn zipf(count: usize, exponent: f64) -> Self {
+        let cumulative = (0..count)
+            .scan(0.0, |acc, k| {
+                *acc += 1.0 / ((k + 1) as f64).powf(exponent);
+                Some(*acc)
+            })
+            .collect();
+        Sampler::Weighted(cumulative)
+    }
+
+    fn pick(&self, rng: &mut Rng) -> usize {
+        match self {
+            Sampler::Range(begin, end) => rng.usize(*begin..*end),
+            Sampler::Weighted(cumulative) => {
+                let x = rng.f64() * cumulative.last().unwrap();
+                cumulative.partition_point(|&c| c <= x).min(cumulative.len() - 1)
+            }
+        }
+    }
+}
+
+/// Segments of stations, to pick destinations on the segment of the source at some rate.
+struct Locality {
+    /// Share of destinations on the segment of the source
+    ratio: f64,
+    /// Segment of each station, by index.
+    seg_of: Vec<usize>,
+    /// Stations on each segment, by index.
+    members: Vec<Vec<usize>>,
+}
+
+/// Traffic matrix over a pool of addresses, picking sources and destinations by index.
+pub struct TrafficMatrix {
+    traffic: Traffic,
+    src: Sampler,
+    dst: Sampler,
+    /// Count of servers (for hotspot and client/server models), in front of the pool.
+    server_cnt: usize,
+    servers: Sampler,
+    clients: Sampler,
+    locality: Option<Locality>,
+}
+
+impl TrafficMatrix {
+    pub fn new(traffic: &Traffic, count: usize) -> Self {
+        let servers = match traffic {
+            Traffic::Hotspot(p) => p.servers,
+            Traffic::ClientServer(p) => p.servers,
+            _ => 0,
+        }.clamp(1, count.saturating_sub(1).max(1));
+        let (src, dst) = match traffic {
+            Traffic::Zipf(p) => (Sampler::zipf(count, p.exponent), Sampler::zipf(count, p.exponent)),
+            _ => (Sampler::Range(0, count), Sampler::Range(0, count)),
+        };
+        TrafficMatrix {
+            traffic: traffic.clone(),
+            src,
+            dst,
+            server_cnt: servers,
+            servers: Sampler::Range(0, servers),
+            clients: Sampler::Range(servers, count.max(servers + 1)),
+            locality: None,
+        }
+    }
+
+    /// Pick destinations on the segment of the source at a ratio, given the segment of each station
+    /// by index, and elsewhere otherwise.
+    ///
+    /// Destinations on the segment are picked evenly, those elsewhere by the traffic matrix.
+    pub fn with_locality(mut self, ratio: f64, seg_of: Vec<usize>) -> Self {
+        let mut members = vec![Vec::new(); seg_of.iter().max().map_or(0, |s| s + 1)];
+        for (index, seg) in seg_of.iter().enumerate() {
+            members[*seg].push(index);
+        }
+        self.locality = Some(Locality { ratio: ratio.clamp(0.0, 1.0), seg_of, members });
+        self
+    }
+
+    /// Pick the index of a source.
+    pub fn pick_src(&self, rng: &mut Rng) -> usize {
+        match &self.traffic {
+            Traffic::ClientServer(p) => {
+                if rng.f64() < p.reply {
+                    self.servers.pick(rng)
+                } else {
+                    self.clients.pick(rng)
+                }
+            }
+            _ => self.src.pick(rng),
+        }
+    }
+
+    /// Pick the index of a destination for a source, other than the source.
+    pub fn pick_dst(&self, rng: &mut Rng, src: usize) -> usize {
+        let Some(locality) = &self.locality else {
+            return self.pick_matrix_dst(rng, src);
+        };
+        let peers = &locality.members[locality.seg_of[src]];
+        if rng.f64() < locality.ratio && peers.len() > 1 {
+            let mut dst = src;
+            while dst == src {
+                dst = peers[rng.usize(0..peers.len())];
+            }
+            return dst;
+        }
+        // elsewhere, giving up after some tries on models that leave no destination there
+        let mut dst = self.pick_matrix_dst(rng, src);
+        for _ in 0..64 {
+            if locality.seg_of[dst] != locality.seg_of[src] {
+                break;
+            }
+            dst = self.pick_matrix_dst(rng, src);
+        }
+        dst
+    }
+
+    /// Pick the index of a destination for a source as the model tells, other than the source.
+    fn pick_matrix_dst(&self, rng: &mut Rng, src: usize) -> usize {
+        let mut dst = src;
+        while dst == src {
+            dst = match &self.traffic {
+                // a lone server never receives from itself
+                Traffic::Hotspot(p) if rng.f64() < p.share && (self.server_cnt > 1 || !self.is_server(src)) => {
+                    self.servers.pick(rng)
+                }
+                Traffic::ClientServer(_) => {
+                    if self.is_server(src) {
+                        self.clients.pick(rng)
+                    } else {
+                        self.servers.pick(rng)
+                    }
+                }
+                _ => self.dst.pick(rng),
+            };
+        }
+        dst
+    }
+
+    fn is_server(&self, index: usize) -> bool {
+        index < self.server_cnt
+    }
+}
@@ -0,0 +1,341 @@
+use std::f64::consts::PI;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use serde::Deserialize;
+
+/// Model of arrival of frames over the duration of simulation.
+///
+/// Every model shapes the rate of arrival, the total count of frames being fixed by the input.
+/// Rates are relative, so only their ratios matter.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum Distribution {
+    /// Constant rate, frames evenly spread.
+    Uniform,
+    /// Rate rising to its peak in the middle, see `half_circle_dist_cdf`.
+    HalfCircle,
+    /// Constant rate with arrivals at random, i.e. a Poisson process.
+    Poisson,
+    /// Poisson process modulated by a Markov chain switching between on and off (MMPP).
+    Mmpp(Mmpp),
+    /// Rate following a sine, like the load of a day.
+    Diurnal(Diurnal),
+    /// Rate jumping to another level at some point.
+    Step(Step),
+    /// Rate changing linearly from one level to another.
+    Ramp(Ramp),
+    /// Bursts and silences of heavy-tailed lengths following Pareto distribution.
+    Pareto(Pareto),
+    /// Rate replayed from a recorded trace of timestamps.
+    Trace(Trace),
+}
+
+/// Parameters of `Distribution::Mmpp`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Mmpp {
+    /// Mean time spent in the on state
+    pub on_ms: f64,
+    /// Mean time spent in the off state
+    pub off_ms: f64,
+    /// Rate in the off state relative to the on state
+    pub off_rate: f64,
+}
+
+impl Default for Mmpp {
+    fn default() -> Self {
+        Mmpp { on_ms: 20.0, off_ms: 80.0, off_rate: 0.1 }
+    }
+}
+
+/// Parameters of `Distribution::Diurnal`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Diurnal {
+    /// Count of periods in the duration, each starting and ending at the lowest rate
+    pub periods: f64,
+    /// Depth of modulation from 0 (constant) to 1 (silent at the lowest)
+    pub depth: f64,
+}
+
+impl Default for Diurnal {
+    fn default() -> Self {
+        Diurnal { periods: 1.0, depth: 0.8 }
+    }
+}
+
+/// Parameters of `Distribution::Step`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Step {
+    /// Point of the jump as fraction of the duration
+    pub at: f64,
+    /// Rate after the jump relative to the rate before it
+    pub ratio: f64,
+}
+
+impl Default for Step {
+    fn default() -> Self {
+        Step { at: 0.5, ratio: 4.0 }
+    }
+}
+
+/// Parameters of `Distribution::Ramp`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Ramp {
+    /// Rate at the beginning
+    pub from: f64,
+    /// Rate at the end
+    pub to: f64,
+}
+
+impl Default for Ramp {
+    fn default() -> Self {
+        Ramp { from: 0.1, to: 1.0 }
+    }
+}
+
+/// Parameters of `Distribution::Pareto`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Pareto {
+    /// Shape of the Pareto distribution, heavier tail when smaller
+    pub shape: f64,
+    /// Shortest burst
+    pub on_ms: f64,
+    /// Shortest silence
+    pub off_ms: f64,
+}
+
+impl Default for Pareto {
+    fn default() -> Self {
+        Pareto { shape: 1.5, on_ms: 5.0, off_ms: 20.0 }
+    }
+}
+
+/// Parameters of `Distribution::Trace`.
+///
+/// The trace is stretched to fit the duration of simulation, set the duration to the length of the
+/// trace to replay it in its original pace.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Trace {
+    /// Text file with one timestamp in seconds per line, `#` starting a comment
+    pub path: PathBuf,
+}
+
+impl FromStr for Distribution {
+    type Err = String;
+
+    /// Parse the name of a model with default parameters, or `trace:<path>` for a trace.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(Distribution::Uniform),
+            "half_circle" => Ok(Distribution::HalfCircle),
+            "poisson" => Ok(Distribution::Poisson),
+            "mmpp" => Ok(Distribution::Mmpp(Mmpp::default())),
+            "diurnal" => Ok(Distribution::Diurnal(Diurnal::default())),
+            "step" => Ok(Distribution::Step(Step::default())),
+            "ramp" => Ok(Distribution::Ramp(Ramp::default())),
+            "pareto" => Ok(Distribution::Pareto(Pareto::default())),
+            _ => match s.strip_prefix("trace:") {
+                Some(path) if !path.is_empty() => Ok(Distribution::Trace(Trace { path: path.into() })),
+                _ => Err(format!("unknown arrival model \"{}\", expected uniform, half_circle, poisson, mmpp, \
+                                  diurnal, step, ramp, pareto or trace:<path>", s)),
+            },
+        }
+    }
+}
+
+impl Distribution {
+    /// Check that parameters describe a model, failing with the reason otherwise.
+    pub fn validate(&self) {
+        match self {
+            Distribution::Mmpp(p) => {
+                assert!(p.on_ms > 0.0 && p.off_ms > 0.0, "invalid mmpp: on_ms and off_ms must be positive");
+            }
+            Distribution::Pareto(p) => {
+                assert!(p.on_ms > 0.0 && p.off_ms > 0.0, "invalid pareto: on_ms and off_ms must be positive");
+                assert!(p.shape > 0.0, "invalid pareto: shape must be positive");
+            }
+            _ => {}
+        }
+    }
+
+    /// Count of frames arriving in each millisecond of the duration.
+    pub fn counts(&self, frame_cnt: usize, dur_ms: usize, rng: &mut fastrand::Rng) -> Vec<usize> {
+        match self {
+            Distribution::Uniform => spread_cdf(frame_cnt, dur_ms, uniform_dist_cdf),
+            Distribution::HalfCircle => spread_cdf(frame_cnt, dur_ms, half_circle_dist_cdf),
+            Distribution::Poisson => scatter(frame_cnt, &vec![1.0; dur_ms], rng),
+            Distribution::Mmpp(p) => {
+                let rates = mmpp_rates(p, dur_ms, rng);
+                scatter(frame_cnt, &rates, rng)
+            }
+            Distribution::Diurnal(p) => spread(frame_cnt, &rates_of(dur_ms, |x| {
+                1.0 - p.depth * (2.0 * PI * p.periods * x).cos()
+            })),
+            Distribution::Step(p) => spread(frame_cnt, &rates_of(dur_ms, |x| {
+                if x < p.at { 1.0 } else { p.ratio }
+            })),
+            Distribution::Ramp(p) => spread(frame_cnt, &rates_of(dur_ms, |x| {
+                p.from + (p.to - p.from) * x
+            })),
+            Distribution::Pareto(p) => {
+                let rates = pareto_rates(p, dur_ms, rng);
+                scatter(frame_cnt, &rates, rng)
+            }
+            Distribution::Trace(p) => spread(frame_cnt, &trace_rates(p, dur_ms)),
+        }
+    }
+}
+
+/// Cumulative distribution function of the uniform distribution.
+pub fn uniform_dist_cdf(x: f64) -> f64 {
+    x
+}
+
+/// Cumulative distribution function of the distribution of "half circle".
+///
+/// Its PDF (Probability Density Function)'s graph will look like one top half of a circle fitted
+/// in the square of x from 0 to 1 and y from 0 to 1.
+pub fn half_circle_dist_cdf(x: f64) -> f64 {
+    let x = x * PI - PI / 2.0;
+    (x.sin() + 1.0) / 2.0
+}
+
+/// Spread frames deterministically with a CDF, each millisecond taking frames up to its beginning
+/// and the last one the rest, as the bridge always spread them.
+fn spread_cdf(frame_cnt: usize, dur_ms: usize, cdf: fn(f64) -> f64) -> Vec<usize> {
+    let mut counts = vec![0; dur_ms];
+    let mut last_pos = 0;
+    for (i, count) in counts.iter_mut().enumerate() {
+        let pos = (cdf(i as f64 / dur_ms as f64) * frame_cnt as f64) as usize;
+        let pos = pos.clamp(last_pos, frame_cnt);
+        *count = pos - last_pos;
+        last_pos = pos;
+    }
+    if let Some(count) = counts.last_mut() {
+        *count += frame_cnt - last_pos;
+    }
+    counts
+}
+
+/// Rates sampled in the middle of each millisecond from a function of time scaled to 0 to 1.
+fn rates_of(dur_ms: usize, rate: impl Fn(f64) -> f64) -> Vec<f64> {
+    (0..dur_ms).map(|i| rate((i as f64 + 0.5) / dur_ms as f64).max(0.0)).collect()
+}
+
+/// Spread frames deterministically in proportion to rates.
+fn spread(frame_cnt: usize, rates: &[f64]) -> Vec<usize> {
+    let total = rates.iter().sum::<f64>();
+    if total <= 0.0 {
+        return spread_cdf(frame_cnt, rates.len(), uniform_dist_cdf);
+    }
+    let mut counts = vec![0; rates.len()];
+    let mut acc = 0.0;
+    let mut last_pos = 0;
+    for (count, rate) in counts.iter_mut().zip(rates) {
+        acc += rate;
+        let pos = ((acc / total * frame_cnt as f64) as usize).min(frame_cnt);
+        *count = pos - last_pos;
+        last_pos = pos;
+    }
+    if let Some(count) = counts.last_mut() {
+        *count += frame_cnt - last_pos;
+    }
+    counts
+}
+
+/// Scatter frames at random in proportion to rates, each frame independently.
+///
+/// With a fixed count of frames, this is a (non-homogeneous) Poisson process over the rates.
+fn scatter(frame_cnt: usize, rates: &[f64], rng: &mut fastrand::Rng) -> Vec<usize> {
+    let cumulative = rates.iter()
+        .scan(0.0, |acc, rate| {
+            *acc += rate;
+            Some(*acc)
+        })
+        .collect::<Vec<_>>();
+    let total = cumulative.last().copied().unwrap_or(0.0);
+    if total <= 0.0 && !rates.is_empty() {
+        return scatter(frame_cnt, &vec![1.0; rates.len()], rng);
+    }
+    let mut counts = vec![0; rates.len()];
+    for _ in 0..frame_cnt {
+        let x = rng.f64() * total;
+        let i = cumulative.partition_point(|&c| c <= x).min(rates.len() - 1);
+        counts[i] += 1;
+    }
+    counts
+}
+
+/// Add a rate lasting from one time to another (in milliseconds) to the rates of milliseconds.
+fn fill(rates: &mut [f64], from: f64, to: f64, rate: f64) {
+    let to = to.min(rates.len() as f64);
+    let mut t = from;
+    while t < to {
+        let i = t as usize;
+        let end = ((i + 1) as f64).min(to);
+        rates[i] += rate * (end - t);
+        t = end;
+    }
+}
+
+fn exp_sample(rng: &mut fastrand::Rng, mean: f64) -> f64 {
+    -mean * (1.0 - rng.f64()).ln()
+}
+
+fn pareto_sample(rng: &mut fastrand::Rng, shape: f64, min: f64) -> f64 {
+    min / (1.0 - rng.f64()).powf(1.0 / shape)
+}
+
+/// Rates of a two-state MMPP, starting in a state chosen by its stationary probability.
+fn mmpp_rates(p: &Mmpp, dur_ms: usize, rng: &mut fastrand::Rng) -> Vec<f64> {
+    let mut rates = vec![0.0; dur_ms];
+    let mut on = rng.f64() < p.on_ms / (p.on_ms + p.off_ms);
+    let mut t = 0.0;
+    while t < dur_ms as f64 {
+        let (mean, rate) = if on { (p.on_ms, 1.0) } else { (p.off_ms, p.off_rate) };
+        let end = t + exp_sample(rng, mean);
+        fill(&mut rates, t, end, rate);
+        t = end;
+        on = !on;
+    }
+    rates
+}
+
+/// Rates of alternating bursts and silences with lengths following Pareto distribution.
+fn pareto_rates(p: &Pareto, dur_ms: usize, rng: &mut fastrand::Rng) -> Vec<f64> {
+    let mut rates = vec![0.0; dur_ms];
+    let mut t = 0.0;
+    while t < dur_ms as f64 {
+        let end = t + pareto_sample(rng, p.shape, p.on_ms);
+        fill(&mut rates, t, end, 1.0);
+        t = end + pareto_sample(rng, p.shape, p.off_ms);
+    }
+    rates
+}
+
+/// Rates of a trace of timestamps, stretched from its first to last timestamp over the duration.
+fn trace_rates(p: &Trace, dur_ms: usize) -> Vec<f64> {
+    let text = fs::read_to_string(&p.path)
+        .unwrap_or_else(|err| panic!("cannot read trace {}: {}", p.path.display(), err));
+    let times = text.lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.parse::<f64>()
+            .unwrap_or_else(|_| panic!("invalid timestamp in trace {}: {}", p.path.display(), line)))
+        .collect::<Vec<_>>();
+    let mut rates = vec![0.0; dur_ms];
+    let first = times.iter().copied().fold(f64::INFINITY, f64::min);
+    let last = times.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let span = (last - first).max(f64::MIN_POSITIVE);
+    for t in times {
+        let i = ((t - first) / span * dur_ms as f64) as usize;
+        rates[i.min(dur_ms - 1)] += 1.0;
+    }
+    rates
+}
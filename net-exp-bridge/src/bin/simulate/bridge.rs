@@ -0,0 +1,751 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use std::str::FromStr;
+use log::info;
+use serde::Deserialize;
+use serde_pickle::SerOptions;
+use net_exp_bridge::{Address, Frame, Segment};
+use net_exp_bridge::fdb::{FdbStats, ForwardingDatabase};
+use crate::{elapsed, Command, Event};
+use crate::queue::{self, Inlet, Queue};
+use crate::scenario::Scenario;
+
+/// Frames to an address waiting for its segment, and broadcasts sent to ask for it.
+#[derive(Default)]
+struct Waiting {
+    /// Frames by order of arrival, with times they were received.
+    frames: BTreeMap<u64, (Frame, u64)>,
+    /// Broadcasts sent so far.
+    attempts: u32,
+    /// Time to broadcast again or give up without an answer, if asked.
+    deadline: Option<u64>,
+}
+
+/// Waiting list of frames, with times they were received.
+struct Holder {
+    map: BTreeMap<Address, Waiting>,
+    /// Deadlines of addresses asked for, earliest first.
+    deadlines: BTreeSet<(u64, Address)>,
+    /// Destinations of all frames by order of arrival, with times they were received.
+    order: BTreeMap<u64, (u64, Address)>,
+    seq: u64,
+}
+
+impl Holder {
+    fn new() -> Self {
+        Holder { map: BTreeMap::new(), deadlines: BTreeSet::new(), order: BTreeMap::new(), seq: 0 }
+    }
+
+    /// Hold a frame received at specified time.
+    fn hold(&mut self, frame: Frame, now: u64) {
+        self.order.insert(self.seq, (now, frame.dst));
+        let waiting = self.map.entry(frame.dst)
+            .or_default();
+        waiting.frames.insert(self.seq, (frame, now));
+        self.seq += 1;
+    }
+
+    /// Count of frames held for an address.
+    fn count(&self, addr: &Address) -> usize {
+        self.map.get(addr).map_or(0, |waiting| waiting.frames.len())
+    }
+
+    /// Count of frames held for all addresses.
+    fn total(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Time the earliest frame held was received.
+    fn oldest_received(&self) -> Option<u64> {
+        self.order.first_key_value().map(|(_, (received, _))| *received)
+    }
+
+    /// Take the earliest frame held for an address, or for any address if `None`.
+    ///
+    /// An address left without frames keeps waiting for its answer, so that frames arriving later
+    /// are not broadcast for again.
+    fn take_oldest(&mut self, addr: Option<Address>) -> Option<(Frame, u64)> {
+        let addr = match addr {
+            Some(addr) => addr,
+            None => self.order.first_key_value()?.1.1,
+        };
+        let (seq, held) = self.map.get_mut(&addr)?.frames.pop_first()?;
+        self.order.remove(&seq);
+        Some(held)
+    }
+
+    /// Note a broadcast asking for an address with frames held, answered by a deadline if any.
+    fn ask(&mut self, addr: Address, deadline: Option<u64>) {
+        let Some(waiting) = self.map.get_mut(&addr) else { return };
+        if let Some(last) = waiting.deadline.take() {
+            self.deadlines.remove(&(last, addr));
+        }
+        waiting.attempts += 1;
+        if let Some(deadline) = deadline {
+            waiting.deadline = Some(deadline);
+            self.deadlines.insert((deadline, addr));
+        }
+    }
+
+    /// Count of broadcasts sent for an address.
+    fn attempts(&self, addr: &Address) -> u32 {
+        self.map.get(addr).map_or(0, |waiting| waiting.attempts)
+    }
+
+    /// Earliest deadline of addresses asked for.
+    fn next_deadline(&self) -> Option<u64> {
+        self.deadlines.first().map(|(deadline, _)| *deadline)
+    }
+
+    /// Take an address whose deadline has passed at specified time, if any.
+    fn overdue(&mut self, now: u64) -> Option<Address> {
+        if self.deadlines.first().is_none_or(|(deadline, _)| *deadline > now) {
+            return None;
+        }
+        let (_, addr) = self.deadlines.pop_first()?;
+        if let Some(waiting) = self.map.get_mut(&addr) {
+            waiting.deadline = None;
+        }
+        Some(addr)
+    }
+
+    /// Release frames of the same address.
+    fn release(&mut self, addr: Address) -> Vec<(Frame, u64)> {
+        let Some(waiting) = self.map.remove(&addr) else { return Vec::new() };
+        if let Some(deadline) = waiting.deadline {
+            self.deadlines.remove(&(deadline, addr));
+        }
+        waiting.frames.into_iter()
+            .map(|(seq, held)| {
+                self.order.remove(&seq);
+                held
+            })
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+}
+
+/// Frame to make room for when the frames held reach a limit.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HoldOverflow {
+    /// Give up the earliest frame held, for the same destination if its limit is reached.
+    DropOldest,
+    /// Give up the frame arriving.
+    DropNewest,
+}
+
+impl FromStr for HoldOverflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop_oldest" => Ok(HoldOverflow::DropOldest),
+            "drop_newest" => Ok(HoldOverflow::DropNewest),
+            _ => Err(format!("unknown hold overflow policy \"{}\", expected drop_oldest or drop_newest", s)),
+        }
+    }
+}
+
+/// Why a frame held for the segment of its destination is given up.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum HoldLoss {
+    /// Held for longer than `hold_timeout_ns`.
+    Timeout,
+    /// Frames held for its destination reached `hold_per_addr`.
+    AddressFull,
+    /// Frames held for all destinations reached `hold_capacity`.
+    HolderFull,
+}
+
+/// How a frame to a group of stations is flooded.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FloodKind {
+    /// To the broadcast address, on all segments.
+    Broadcast,
+    /// To a group without members known by snooping, on all segments.
+    Multicast,
+    /// To a group, on segments where its members reported from.
+    Snooped,
+}
+
+/// Statistics of bridge
+pub enum BridgeStatRecord {
+    /// Frame to an unknown unicast address held while its segment is asked for.
+    Broadcast(Frame),
+    Dispatch(Frame),
+    Discard(Frame),
+    /// Frame to a station on the segment it comes from, not forwarded.
+    Filter(Frame),
+    /// Frame to a group flooded to a count of segments.
+    Flood(FloodKind, usize),
+    /// Frame held for its destination and given up.
+    Abandon(Frame, HoldLoss),
+}
+
+/// Record of bridge statistics.
+///
+/// Times are nanoseconds since the beginning of simulation.
+pub struct BridgeStat {
+    pub records: Vec<BridgeStatRecord>,
+    pub times: Vec<u64>,
+}
+
+impl BridgeStat {
+    fn new() -> Self {
+        BridgeStat { records: Vec::new(), times: Vec::new() }
+    }
+
+    fn broadcast(&mut self, frame: Frame, now: u64) {
+        self.records.push(BridgeStatRecord::Broadcast(frame));
+        self.times.push(now);
+    }
+
+    fn dispatch(&mut self, frame: Frame, now: u64) {
+        self.records.push(BridgeStatRecord::Dispatch(frame));
+        self.times.push(now);
+    }
+
+    fn discard(&mut self, frame: Frame, now: u64) {
+        self.records.push(BridgeStatRecord::Discard(frame));
+        self.times.push(now);
+    }
+
+    fn filter(&mut self, frame: Frame, now: u64) {
+        self.records.push(BridgeStatRecord::Filter(frame));
+        self.times.push(now);
+    }
+
+    fn flood(&mut self, kind: FloodKind, segments: usize, now: u64) {
+        self.records.push(BridgeStatRecord::Flood(kind, segments));
+        self.times.push(now);
+    }
+
+    fn abandon(&mut self, frame: Frame, loss: HoldLoss, now: u64) {
+        self.records.push(BridgeStatRecord::Abandon(frame, loss));
+        self.times.push(now);
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Export scatter of different types of activities.
+    fn export_activity_scatter(&self, scenario: &Scenario) {
+        let sc_src = self.records.iter()
+            .zip(self.times.iter())
+            .map(|(x, y)| (x, y / 1000));
+
+        let mut sc_broadcast = Vec::with_capacity(self.records.len());
+        let mut sc_dispatch = Vec::with_capacity(self.records.len());
+        let mut sc_discard = Vec::with_capacity(self.records.len());
+        let mut sc_filter = Vec::with_capacity(self.records.len());
+        let mut sc_flood = Vec::new();
+        let mut sc_abandon = Vec::new();
+
+        for (x, y) in sc_src {
+            match x {
+                BridgeStatRecord::Broadcast(_) => sc_broadcast.push(y as i64),
+                BridgeStatRecord::Dispatch(_) => sc_dispatch.push(y as i64),
+                BridgeStatRecord::Discard(_) => sc_discard.push(y as i64),
+                BridgeStatRecord::Filter(_) => sc_filter.push(y as i64),
+                BridgeStatRecord::Flood(kind, segments) => {
+                    sc_flood.push(vec![y as i64, *kind as i64, *segments as i64]);
+                }
+                BridgeStatRecord::Abandon(_, loss) => sc_abandon.push(vec![y as i64, *loss as i64]),
+            }
+        }
+
+        let mut w_broadcast = BufWriter::new(File::create(scenario.out_path("sc_broadcast_activity.pkl")).unwrap());
+        let mut w_dispatch = BufWriter::new(File::create(scenario.out_path("sc_dispatch_activity.pkl")).unwrap());
+        let mut w_discard = BufWriter::new(File::create(scenario.out_path("sc_discard_activity.pkl")).unwrap());
+        let mut w_filter = BufWriter::new(File::create(scenario.out_path("sc_filter_activity.pkl")).unwrap());
+        let mut w_flood = BufWriter::new(File::create(scenario.out_path("sc_flood_activity.pkl")).unwrap());
+        let mut w_abandon = BufWriter::new(File::create(scenario.out_path("sc_abandon_activity.pkl")).unwrap());
+
+        serde_pickle::to_writer(&mut w_broadcast, &sc_broadcast, SerOptions::default()).unwrap();
+        serde_pickle::to_writer(&mut w_dispatch, &sc_dispatch, SerOptions::default()).unwrap();
+        serde_pickle::to_writer(&mut w_discard, &sc_discard, SerOptions::default()).unwrap();
+        serde_pickle::to_writer(&mut w_filter, &sc_filter, SerOptions::default()).unwrap();
+        serde_pickle::to_writer(&mut w_flood, &sc_flood, SerOptions::default()).unwrap();
+        serde_pickle::to_writer(&mut w_abandon, &sc_abandon, SerOptions::default()).unwrap();
+    }
+
+    /// Export scatter of latencies of frames broadcast.
+    fn export_latency_scatter(&self, scenario: &Scenario) {
+        let mut hold_map = HashMap::<Frame, u64>::new();
+        let mut latencies = Vec::with_capacity(self.records.len());
+        for (rec, t) in self.records.iter().zip(self.times.iter()) {
+            let t = t / 1000;
+            match rec {
+                BridgeStatRecord::Broadcast(frame) => {
+                    hold_map.insert(frame.clone(), t);
+                }
+                BridgeStatRecord::Dispatch(frame) | BridgeStatRecord::Discard(frame) |
+                BridgeStatRecord::Filter(frame) | BridgeStatRecord::Abandon(frame, _) => {
+                    let begin = if let Some(val) = hold_map.remove(frame) { val } else {
+                        continue
+                    };
+                    let lat = t - begin;
+                    latencies.push(vec![begin as i64, lat as i64]);
+                }
+                BridgeStatRecord::Flood(..) => {}
+            }
+        }
+        serde_pickle::to_writer(&mut BufWriter::new(File::create(scenario.out_path("sc_latency.pkl")).unwrap()),
+                                &latencies, SerOptions::default()).unwrap();
+    }
+}
+
+/// Statistics of pending frames of bridge.
+pub struct BridgePendingStat {
+    pub records: Vec<usize>,
+    pub times: Vec<u64>,
+}
+
+impl BridgePendingStat {
+    fn new() -> Self {
+        BridgePendingStat { records: Vec::new(), times: Vec::new() }
+    }
+
+    fn rec(&mut self, count: usize, now: u64) {
+        self.records.push(count);
+        self.times.push(now);
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Export scatter of congestion, the changing pressure of waiting list.
+    fn export_congestion_scatter(&self, scenario: &Scenario) {
+        let sc_congestion = self.records.iter()
+            .zip(self.times.iter())
+            .map(|(x, y)| vec![(y / 1000) as i64, *x as i64])
+            .collect::<Vec<_>>();
+        serde_pickle::to_writer(&mut BufWriter::new(File::create(scenario.out_path("sc_congestion.pkl")).unwrap()),
+                                &sc_congestion, SerOptions::default()).unwrap();
+    }
+}
+
+/// Statistics of the forwarding table, sampled at each sweep.
+pub struct BridgeTableStat {
+    pub records: Vec<(usize, FdbStats, usize)>,
+    pub times: Vec<u64>,
+}
+
+impl BridgeTableStat {
+    fn new() -> Self {
+        BridgeTableStat { records: Vec::new(), times: Vec::new() }
+    }
+
+    /// Record a sweep with count of evicted entries, counters and size of the table after it.
+    fn rec(&mut self, aged: usize, stats: FdbStats, size: usize, now: u64) {
+        self.records.push((aged, stats, size));
+        self.times.push(now);
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Export scatter of aging, the entries evicted per sweep and the size of the table.
+    fn export_aging_scatter(&self, scenario: &Scenario) {
+        let sc_aging = self.records.iter()
+            .zip(self.times.iter())
+            .map(|((aged, _, size), t)| vec![(t / 1000) as i64, *aged as i64, *size as i64])
+            .collect::<Vec<_>>();
+        serde_pickle::to_writer(&mut BufWriter::new(File::create(scenario.out_path("sc_aging.pkl")).unwrap()),
+                                &sc_aging, SerOptions::default()).unwrap();
+    }
+
+    /// Export scatter of cumulative hits, misses, capacity evictions and aged entries of the table.
+    fn export_table_scatter(&self, scenario: &Scenario) {
+        let sc_table = self.records.iter()
+            .zip(self.times.iter())
+            .map(|((_, stats, _), t)| {
+                vec![(t / 1000) as i64, stats.hits as i64, stats.misses as i64, stats.evicted as i64,
+                     stats.aged as i64]
+            })
+            .collect::<Vec<_>>();
+        serde_pickle::to_writer(&mut BufWriter::new(File::create(scenario.out_path("sc_table.pkl")).unwrap()),
+                                &sc_table, SerOptions::default()).unwrap();
+    }
+}
+
+/// Meter to count bridge activities within some time.
+struct BridgeMeter {
+    req_cnt: usize,
+    /// Frames to unknown unicast addresses, whose segments are asked for.
+    b_cnt: usize,
+    dp_cnt: usize,
+    dc_cnt: usize,
+    ft_cnt: usize,
+    ev_cnt: usize,
+    mv_cnt: usize,
+    /// Frames flooded to groups, by kind.
+    fl_cnt: [usize; 3],
+    /// Frames dropped at the ingress queue.
+    dr_cnt: usize,
+    /// Broadcasts sent again without an answer in time.
+    rt_cnt: usize,
+    /// Addresses given up on after all broadcasts.
+    to_cnt: usize,
+    /// Frames held and given up.
+    ab_cnt: usize,
+}
+
+impl BridgeMeter {
+    fn new() -> Self {
+        BridgeMeter { req_cnt: 0, b_cnt: 0, dp_cnt: 0, dc_cnt: 0, ft_cnt: 0, ev_cnt: 0, mv_cnt: 0, fl_cnt: [0; 3],
+            dr_cnt: 0, rt_cnt: 0, to_cnt: 0, ab_cnt: 0 }
+    }
+
+    fn report(&mut self) {
+        info!(target: "bridge", "Received {} requests. Done {} broadcasts of unknown unicasts, {} dispatches, \
+            {} discards and {} filters. Flooded {} broadcasts and {} multicasts, sent {} multicasts to members. \
+            Aged out {} entries and relearned {}. Dropped {} frames at ingress. Broadcast {} again and gave up \
+            on {} addresses and {} frames held.", self.req_cnt, self.b_cnt, self.dp_cnt, self.dc_cnt, self.ft_cnt,
+            self.fl_cnt[0], self.fl_cnt[1], self.fl_cnt[2], self.ev_cnt, self.mv_cnt, self.dr_cnt, self.rt_cnt,
+            self.to_cnt, self.ab_cnt);
+        *self = BridgeMeter::new();
+    }
+}
+
+/// Network bridge, learning segments of addresses from the frames it forwards.
+pub struct Bridge {
+    scenario: Scenario,
+    mapping: ForwardingDatabase,
+    pending: Holder,
+    /// Queue of events arriving at the bridge.
+    ingress: Queue,
+    stat: BridgeStat,
+    pending_stat: BridgePendingStat,
+    table_stat: BridgeTableStat,
+    meter: BridgeMeter,
+    /// Segments attached to the bridge.
+    segments: Vec<Segment>,
+    /// Multicast groups with the segments their members reported from, learned by snooping.
+    groups: BTreeMap<Address, BTreeMap<Address, Segment>>,
+    fl_total: [usize; 3],
+    ft_total: usize,
+    mv_total: usize,
+    rt_total: usize,
+    to_total: usize,
+    /// Frames held and given up, by reason.
+    ab_total: [usize; 3],
+    last_t: u64,
+    last_sweep: u64,
+}
+
+impl Bridge {
+    pub fn new(scenario: &Scenario, segments: Vec<Segment>) -> Self {
+        let mapping = ForwardingDatabase::new(
+            scenario.table_capacity, scenario.aging_ms * 1000, scenario.eviction_policy);
+        Bridge {
+            scenario: scenario.clone(),
+            mapping: mapping.with_seed(scenario.seed),
+            pending: Holder::new(),
+            ingress: Queue::new(scenario.ingress_capacity, scenario.queue_policy.clone(), scenario.seed),
+            stat: BridgeStat::new(),
+            pending_stat: BridgePendingStat::new(),
+            table_stat: BridgeTableStat::new(),
+            meter: BridgeMeter::new(),
+            segments,
+            groups: BTreeMap::new(),
+            fl_total: [0; 3],
+            ft_total: 0,
+            mv_total: 0,
+            rt_total: 0,
+            to_total: 0,
+            ab_total: [0; 3],
+            last_t: 0,
+            last_sweep: 0,
+        }
+    }
+
+    /// Dispatch a frame received at some time to the segment of its destination, or filter it if that
+    /// is where it comes from.
+    fn forward(&mut self, frame: Frame, segment: Segment, received: u64, now: u64, tc: &mut impl FnMut(Command)) {
+        if segment == frame.src_seg {
+            // both stations share the medium, the destination has it already
+            self.stat.filter(frame.clone(), now);
+            tc(Command::Filter(frame));
+            self.meter.ft_cnt += 1;
+            self.ft_total += 1;
+        } else {
+            self.stat.dispatch(frame.clone(), now);
+            tc(Command::Dispatch(frame, segment, received));
+            self.meter.dp_cnt += 1;
+        }
+    }
+
+    /// Flood a frame to the broadcast address or a multicast group, to the segments where members
+    /// of the group reported from if snooping knows them, or to all segments but the one it comes from.
+    fn flood(&mut self, frame: Frame, now: u64, tc: &mut impl FnMut(Command)) {
+        let members = match self.scenario.igmp_snooping && !frame.dst.is_broadcast() {
+            true => self.groups.get(&frame.dst),
+            false => None,
+        };
+        let (kind, segments) = match members {
+            Some(members) => {
+                let segments = members.values().copied().collect::<BTreeSet<_>>();
+                (FloodKind::Snooped, segments.into_iter().filter(|s| *s != frame.src_seg).collect::<Vec<_>>())
+            }
+            None => {
+                let kind = if frame.dst.is_broadcast() { FloodKind::Broadcast } else { FloodKind::Multicast };
+                (kind, self.segments.iter().copied().filter(|s| *s != frame.src_seg).collect())
+            }
+        };
+        self.stat.flood(kind, segments.len(), now);
+        self.meter.fl_cnt[kind as usize] += 1;
+        self.fl_total[kind as usize] += 1;
+        tc(Command::Flood(frame, segments, now));
+    }
+
+    /// Offer an event arriving at specified time to the ingress queue holding a count of events.
+    ///
+    /// Returns the event if admitted, or tells the facility about the frame it carries if dropped.
+    pub fn enqueue(&mut self, event: Event, depth: usize, now: u64, tc: &mut impl FnMut(Command)) -> Option<Event> {
+        let priority = match &event {
+            Event::Request(frame) => Some(queue::priority(frame)),
+            _ => None,
+        };
+        if self.ingress.admit(depth, priority, now) {
+            return Some(event);
+        }
+        if let Event::Request(frame) = event {
+            tc(Command::Drop(frame));
+        }
+        self.meter.dr_cnt += 1;
+        None
+    }
+
+    /// Give up a frame held for its destination.
+    fn abandon(&mut self, frame: Frame, loss: HoldLoss, now: u64, tc: &mut impl FnMut(Command)) {
+        self.stat.abandon(frame.clone(), loss, now);
+        tc(Command::Abandon(frame));
+        self.meter.ab_cnt += 1;
+        self.ab_total[loss as usize] += 1;
+    }
+
+    /// Hold a frame received at specified time while the segment of its destination is asked for,
+    /// making room by the overflow policy if frames held reach a limit.
+    ///
+    /// Returns `false` if the frame is given up instead.
+    fn hold(&mut self, frame: Frame, now: u64, tc: &mut impl FnMut(Command)) -> bool {
+        let dst = frame.dst;
+        let limits = [
+            (HoldLoss::AddressFull, self.scenario.hold_per_addr, Some(dst)),
+            (HoldLoss::HolderFull, self.scenario.hold_capacity, None),
+        ];
+        for (loss, limit, addr) in limits {
+            let count = if addr.is_some() { self.pending.count(&dst) } else { self.pending.total() };
+            if limit == 0 || count < limit {
+                continue;
+            }
+            match self.scenario.hold_overflow {
+                HoldOverflow::DropNewest => {
+                    self.abandon(frame, loss, now, tc);
+                    return false;
+                }
+                HoldOverflow::DropOldest => {
+                    if let Some((oldest, _)) = self.pending.take_oldest(addr) {
+                        self.abandon(oldest, loss, now, tc);
+                    }
+                }
+            }
+        }
+        self.stat.broadcast(frame.clone(), now);
+        self.pending_stat.rec(self.pending.len(), now);
+        self.pending.hold(frame, now);
+        true
+    }
+
+    /// Broadcast to ask the segments for an address with frames held, waiting for an answer until
+    /// the timeout, or for ever if there is none.
+    fn ask(&mut self, addr: Address, now: u64, tc: &mut impl FnMut(Command)) {
+        tc(Command::Broadcast(addr));
+        let timeout = self.scenario.broadcast_timeout_ns;
+        self.pending.ask(addr, Some(now + timeout).filter(|_| timeout > 0));
+    }
+
+    /// Discard all frames held for an address.
+    fn discard(&mut self, addr: Address, now: u64, tc: &mut impl FnMut(Command)) {
+        for (frame, _) in self.pending.release(addr) {
+            self.stat.discard(frame.clone(), now);
+            tc(Command::Discard(frame));
+            self.meter.dc_cnt += 1;
+        }
+        self.pending_stat.rec(self.pending.len(), now);
+    }
+
+    /// Earliest time an answer to a broadcast is overdue or a frame is held for too long, to call
+    /// `expire` then.
+    pub fn next_deadline(&self) -> Option<u64> {
+        let timeout = self.scenario.hold_timeout_ns;
+        let held = self.pending.oldest_received().filter(|_| timeout > 0).map(|received| received + timeout);
+        match (self.pending.next_deadline(), held) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Give up frames held for too long at specified time, then broadcast again for addresses not
+    /// answered in time, or give up on those broadcast for as many times as allowed and discard
+    /// their frames.
+    pub fn expire(&mut self, now: u64, tc: &mut impl FnMut(Command)) {
+        let timeout = self.scenario.hold_timeout_ns;
+        if timeout > 0 {
+            while self.pending.oldest_received().is_some_and(|received| received + timeout <= now) {
+                let (frame, _) = self.pending.take_oldest(None).unwrap();
+                self.abandon(frame, HoldLoss::Timeout, now, tc);
+            }
+        }
+        while let Some(addr) = self.pending.overdue(now) {
+            if self.pending.attempts(&addr) <= self.scenario.broadcast_retries {
+                self.ask(addr, now, tc);
+                self.meter.rt_cnt += 1;
+                self.rt_total += 1;
+            } else {
+                self.discard(addr, now, tc);
+                self.meter.to_cnt += 1;
+                self.to_total += 1;
+            }
+        }
+    }
+
+    /// Handle an event at specified time, emitting commands to the facility.
+    ///
+    /// Returns `false` if the bridge is shutting down.
+    pub fn handle(&mut self, event: Event, now: u64, tc: &mut impl FnMut(Command)) -> bool {
+        let now_us = now / 1000;
+        match event {
+            Event::Request(frame) => {
+                // correlate the source address with incoming segment
+                if self.mapping.learn(frame.src, frame.src_seg, now_us) {
+                    self.meter.mv_cnt += 1;
+                    self.mv_total += 1;
+                }
+                if frame.dst.is_multicast() {
+                    // to a group of stations, never learned as a source
+                    self.flood(frame, now, tc);
+                } else if let Some(segment) = self.mapping.lookup(&frame.dst, now_us) {
+                    // dispatch if source found in mapping
+                    self.forward(frame, segment, now, now, tc);
+                    self.meter.req_cnt += 1;
+                } else {
+                    let dst = frame.dst;
+                    if self.hold(frame, now, tc) && self.pending.attempts(&dst) == 0 {
+                        // broadcast if no frames of same source were waiting
+                        self.ask(dst, now, tc); // <- actual command
+                        self.meter.b_cnt += 1;
+                    }
+                }
+            }
+            Event::Success(address, segment) => {
+                // update the mapping
+                self.mapping.learn(address, segment, now_us);
+                for (frame, received) in self.pending.release(address) {
+                    // dispatch all frames with the same segment
+                    self.forward(frame, segment, received, now, tc);
+                }
+                self.pending_stat.rec(self.pending.len(), now);
+            }
+            Event::Failure(address) => {
+                // discard them all
+                self.discard(address, now, tc);
+            }
+            Event::Migrate(address, segment) => {
+                // not observable by the bridge, just pass it along
+                tc(Command::Migrate(address, segment));
+            }
+            Event::Report(membership) => {
+                // snoop on the report, then pass it along
+                if membership.join {
+                    self.groups.entry(membership.group).or_default().insert(membership.addr, membership.seg);
+                } else if let Some(members) = self.groups.get_mut(&membership.group) {
+                    members.remove(&membership.addr);
+                    if members.is_empty() {
+                        self.groups.remove(&membership.group);
+                    }
+                }
+                tc(Command::Report(membership));
+            }
+            Event::Shutdown => {
+                info!(target: "bridge", "Received shutdown signal.");
+                return false;
+            }
+        }
+        self.expire(now, tc);
+        if now - self.last_sweep > self.scenario.sweep_ms * 1_000_000 {
+            // forget the addresses not seen within aging time
+            let aged = self.mapping.sweep(now_us);
+            self.table_stat.rec(aged, self.mapping.stats(), self.mapping.len(), now);
+            self.meter.ev_cnt += aged;
+            self.last_sweep = now;
+        }
+        if now - self.last_t > self.scenario.bridge_log_ms * 1_000_000 {
+            self.meter.report();
+            self.last_t = now;
+        }
+        true
+    }
+
+    /// Export statistics.
+    pub fn export(&self) {
+        let fdb = self.mapping.stats();
+        info!(target: "bridge", "Recorded {} activities, {} congestion samples and {} sweeps. \
+            Relearned {} moved stations and filtered {} frames.", self.stat.len(), self.pending_stat.len(),
+            self.table_stat.len(), self.mv_total, self.ft_total);
+        info!(target: "bridge", "Broadcast {} times again without an answer in time, gave up on {} addresses.",
+            self.rt_total, self.to_total);
+        info!(target: "bridge", "Gave up {} frames held for longer than timeout, {} for their destination and {} \
+            for all destinations at limit ({} and {} frames, {:?}).", self.ab_total[HoldLoss::Timeout as usize],
+            self.ab_total[HoldLoss::AddressFull as usize], self.ab_total[HoldLoss::HolderFull as usize],
+            self.scenario.hold_per_addr, self.scenario.hold_capacity, self.scenario.hold_overflow);
+        info!(target: "bridge", "Flooded {} broadcasts and {} multicasts, sent {} multicasts to members of \
+            {} groups.", self.fl_total[0], self.fl_total[1], self.fl_total[2], self.groups.len());
+        info!(target: "bridge", "Dropped {} frames at ingress (capacity {}, {:?}).", self.ingress.dropped(),
+            self.ingress.capacity(), self.scenario.queue_policy);
+        info!(target: "bridge", "Table ({:?}, {} entries at most): {} hits, {} misses (hit rate {:.4}), \
+            {} evicted and {} aged out.", self.mapping.policy(), self.mapping.capacity(), fdb.hits,
+            fdb.misses, fdb.hit_rate(), fdb.evicted, fdb.aged);
+        self.stat.export_activity_scatter(&self.scenario);
+        self.stat.export_latency_scatter(&self.scenario);
+        self.pending_stat.export_congestion_scatter(&self.scenario);
+        self.table_stat.export_aging_scatter(&self.scenario);
+        self.table_stat.export_table_scatter(&self.scenario);
+        self.ingress.export(&self.scenario, "ingress");
+    }
+}
+
+/// Launch network bridge
+pub fn bridge(origin: Instant, mut bridge: Bridge, tc: Inlet<Command>, re: Receiver<Event>) {
+    info!(target: "bridge", "Bridge started.");
+    let mut tc = |command| tc.send(command).unwrap();
+    let mut pending = VecDeque::new();
+    loop {
+        // receive an event, those arrived while busy waiting in the ingress queue, until an answer is overdue
+        let wait = bridge.next_deadline()
+            .map(|deadline| Duration::from_nanos(deadline.saturating_sub(elapsed(origin))));
+        let event = queue::receive(&re, &mut pending, wait, |event, depth| {
+            bridge.enqueue(event, depth, elapsed(origin), &mut tc)
+        });
+        match event {
+            Ok(event) => if !bridge.handle(event, elapsed(origin), &mut tc) {
+                break;
+            },
+            Err(RecvTimeoutError::Timeout) => bridge.expire(elapsed(origin), &mut tc),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    bridge.export();
+    info!(target: "bridge", "Bridge exiting.");
+}
@@ -0,0 +1,181 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, VecDeque};
+use log::info;
+use net_exp_bridge::{Address, Membership, Migration, Segment};
+use crate::bridge::Bridge;
+use crate::facility::Facility;
+use crate::orchestrator::{distribute, Sequencer};
+use crate::scenario::Scenario;
+use crate::{Command, Event, Frames};
+
+/// Job to be done at some point of simulated time.
+enum Job {
+    /// Orchestrator sends frames of a bucket (one millisecond).
+    Tick(usize),
+    /// Event arrives at the bridge.
+    Bridge(Event),
+    /// Command arrives at the facility.
+    Facility(Command),
+    /// Answers to broadcasts become overdue at the bridge.
+    Expire,
+}
+
+/// Job scheduled at a time, ordered by time and then by order of scheduling.
+struct Scheduled<J> {
+    time: u64,
+    seq: u64,
+    job: J,
+}
+
+impl<J> PartialEq for Scheduled<J> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.time, self.seq) == (other.time, other.seq)
+    }
+}
+
+impl<J> Eq for Scheduled<J> {}
+
+impl<J> PartialOrd for Scheduled<J> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<J> Ord for Scheduled<J> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.time, self.seq).cmp(&(other.time, other.seq))
+    }
+}
+
+/// Priority queue of jobs keyed on simulated time.
+pub struct Engine<J> {
+    queue: BinaryHeap<Reverse<Scheduled<J>>>,
+    seq: u64,
+}
+
+impl<J> Engine<J> {
+    pub fn new() -> Self {
+        Engine { queue: BinaryHeap::new(), seq: 0 }
+    }
+
+    /// Schedule a job, after all jobs already scheduled at the same time.
+    pub fn schedule(&mut self, time: u64, job: J) {
+        self.queue.push(Reverse(Scheduled { time, seq: self.seq, job }));
+        self.seq += 1;
+    }
+
+    /// Take the earliest job with its time.
+    pub fn next(&mut self) -> Option<(u64, J)> {
+        self.queue.pop().map(|Reverse(s)| (s.time, s.job))
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+/// Schedule expiry at the bridge when the earliest answer becomes overdue, unless already scheduled
+/// by then.
+fn schedule_expiry(engine: &mut Engine<Job>, bridge: &Bridge, expire_at: &mut Option<u64>) {
+    if let Some(deadline) = bridge.next_deadline() {
+        if expire_at.is_none_or(|at| deadline < at) {
+            engine.schedule(deadline, Job::Expire);
+            *expire_at = Some(deadline);
+        }
+    }
+}
+
+/// Run the simulation on a virtual clock, with the bridge and the facility as single servers.
+///
+/// Same input and seed always produce the same statistics.
+pub fn run(scenario: &Scenario, frames: Frames, migrations: Vec<Migration>, memberships: Vec<Membership>,
+           mapping: BTreeMap<Address, Segment>) {
+    info!(target: "des", "Discrete event simulation started.");
+    let mut bridge = Bridge::new(scenario, crate::attached_segments(&mapping, &migrations));
+    let mut facility = Facility::new(scenario, frames.len(), mapping);
+    let buckets = distribute(frames.len(), scenario.elapse_sec, &scenario.distribution, scenario.seed);
+    let mut sequencer = Sequencer::new(frames, migrations, memberships);
+    let mut engine = Engine::new();
+    let mut bridge_free = 0;
+    let mut facility_free = 0;
+    // times when the bridge and the facility begin to serve what waits in their queues
+    let mut bridge_queue = VecDeque::new();
+    let mut facility_queue = VecDeque::new();
+    let mut out = Vec::new();
+    // time of the earliest expiry scheduled
+    let mut expire_at = None;
+    engine.schedule(0, Job::Tick(0));
+    while let Some((now, job)) = engine.next() {
+        match job {
+            Job::Tick(i) => {
+                // spread the frames of the bucket evenly within the millisecond
+                let mut events = Vec::new();
+                sequencer.send(buckets[i], &mut |event| events.push(event));
+                let step = 1_000_000 / events.len().max(1) as u64;
+                for (k, event) in events.into_iter().enumerate() {
+                    engine.schedule(now + k as u64 * step, Job::Bridge(event));
+                }
+                if i + 1 < buckets.len() {
+                    engine.schedule(now + 1_000_000, Job::Tick(i + 1));
+                }
+                if (i as u64).is_multiple_of(scenario.orchestrator_log_ms) {
+                    info!(target: "des", "Simulated {} ms, {} jobs queued.", i, engine.len());
+                }
+            }
+            Job::Bridge(event) => {
+                while bridge_queue.front().is_some_and(|begin| *begin <= now) {
+                    bridge_queue.pop_front();
+                }
+                let Some(event) = bridge.enqueue(event, bridge_queue.len(), now, &mut |command| out.push(command))
+                else {
+                    for command in out.drain(..) {
+                        engine.schedule(now, Job::Facility(command));
+                    }
+                    continue;
+                };
+                bridge_queue.push_back(bridge_free.max(now));
+                bridge_free = bridge_free.max(now) + scenario.bridge_service_ns;
+                let running = bridge.handle(event, bridge_free, &mut |command| out.push(command));
+                for command in out.drain(..) {
+                    engine.schedule(bridge_free, Job::Facility(command));
+                }
+                if !running {
+                    break;
+                }
+                schedule_expiry(&mut engine, &bridge, &mut expire_at);
+            }
+            Job::Expire => {
+                if expire_at != Some(now) {
+                    // superseded by an earlier one
+                    continue;
+                }
+                expire_at = None;
+                let now = bridge_free.max(now);
+                bridge.expire(now, &mut |command| out.push(command));
+                for command in out.drain(..) {
+                    engine.schedule(now, Job::Facility(command));
+                }
+                schedule_expiry(&mut engine, &bridge, &mut expire_at);
+            }
+            Job::Facility(command) => {
+                while facility_queue.front().is_some_and(|begin| *begin <= now) {
+                    facility_queue.pop_front();
+                }
+                let mut events = Vec::new();
+                let command = facility.enqueue(command, facility_queue.len(), now,
+                                               &mut |due, event| events.push((due, event)));
+                if let Some(command) = command {
+                    facility_queue.push_back(facility_free.max(now));
+                    facility_free = facility_free.max(now) + scenario.facility_service_ns;
+                    facility.handle(command, facility_free, &mut |due, event| events.push((due, event)));
+                }
+                for (due, event) in events {
+                    engine.schedule(due, Job::Bridge(event));
+                }
+            }
+        }
+    }
+    bridge.export();
+    facility.export();
+    info!(target: "des", "Discrete event simulation finished.");
+}
@@ -0,0 +1,562 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs::File;
+use std::io::BufWriter;
+use std::f64::consts::PI;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::str::FromStr;
+use log::{info, trace};
+use serde::Deserialize;
+use serde_pickle::SerOptions;
+use net_exp_bridge::{Address, Frame, Segment};
+use crate::{elapsed, Command, Event};
+use crate::link::{Link, LinkStat};
+use crate::queue::{self, Inlet, Queue};
+use crate::scenario::Scenario;
+
+/// Model of time for the segments to answer a broadcast, `broadcast_delay_ns` on average.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum ReplyDelay {
+    /// Always the mean.
+    Constant,
+    /// Uniform around the mean.
+    Uniform(Uniform),
+    /// Exponential, most answers quick and some much later.
+    Exponential,
+    /// Log-normal, heavier tail when spread wider.
+    LogNormal(LogNormal),
+}
+
+/// Parameters of `ReplyDelay::Uniform`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Uniform {
+    /// Largest deviation from the mean as fraction of it, up to 1
+    pub spread: f64,
+}
+
+impl Default for Uniform {
+    fn default() -> Self {
+        Uniform { spread: 0.5 }
+    }
+}
+
+/// Parameters of `ReplyDelay::LogNormal`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogNormal {
+    /// Standard deviation of the logarithm of delay
+    pub sigma: f64,
+}
+
+impl Default for LogNormal {
+    fn default() -> Self {
+        LogNormal { sigma: 0.5 }
+    }
+}
+
+impl FromStr for ReplyDelay {
+    type Err = String;
+
+    /// Parse the name of a model with default parameters.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "constant" => Ok(ReplyDelay::Constant),
+            "uniform" => Ok(ReplyDelay::Uniform(Uniform::default())),
+            "exponential" => Ok(ReplyDelay::Exponential),
+            "log_normal" => Ok(ReplyDelay::LogNormal(LogNormal::default())),
+            _ => Err(format!("unknown reply delay model \"{}\", expected constant, uniform, exponential \
+                              or log_normal", s)),
+        }
+    }
+}
+
+impl ReplyDelay {
+    /// Draw a delay of specified mean.
+    pub fn sample(&self, mean_ns: u64, rng: &mut fastrand::Rng) -> u64 {
+        let mean = mean_ns as f64;
+        let delay = match self {
+            ReplyDelay::Constant => mean,
+            ReplyDelay::Uniform(p) => mean * (1.0 + p.spread.min(1.0) * (2.0 * rng.f64() - 1.0)),
+            ReplyDelay::Exponential => -mean * (1.0 - rng.f64()).ln(),
+            ReplyDelay::LogNormal(p) => {
+                // standard normal by Box-Muller, shifted to keep the mean
+                let z = (-2.0 * (1.0 - rng.f64()).ln()).sqrt() * (2.0 * PI * rng.f64()).cos();
+                (mean.max(1.0).ln() - p.sigma * p.sigma / 2.0 + p.sigma * z).exp()
+            }
+        };
+        delay.max(0.0) as u64
+    }
+}
+
+/// Meter to count facility statistics within some time.
+struct FacilityMeter {
+    s_cnt: usize,
+    f_cnt: usize,
+    dp_cnt: usize,
+    dc_cnt: usize,
+    md_cnt: usize,
+    mg_cnt: usize,
+    fl_cnt: usize,
+    ms_cnt: usize,
+    ft_cnt: usize,
+    mf_cnt: usize,
+    dr_cnt: usize,
+    ls_cnt: usize,
+    ab_cnt: usize,
+}
+
+impl FacilityMeter {
+    fn new() -> Self {
+        FacilityMeter { s_cnt: 0, f_cnt: 0, dp_cnt: 0, dc_cnt: 0, md_cnt: 0, mg_cnt: 0, fl_cnt: 0, ms_cnt: 0, ft_cnt: 0,
+            mf_cnt: 0, dr_cnt: 0, ls_cnt: 0, ab_cnt: 0 }
+    }
+
+    fn inc_success(&mut self) {
+        self.s_cnt += 1;
+    }
+
+    fn inc_failure(&mut self) {
+        self.f_cnt += 1;
+    }
+
+    fn inc_dispatch(&mut self) {
+        self.dp_cnt += 1;
+    }
+
+    fn inc_discard(&mut self) {
+        self.dc_cnt += 1;
+    }
+
+    fn inc_misdelivery(&mut self) {
+        self.md_cnt += 1;
+    }
+
+    fn inc_migration(&mut self) {
+        self.mg_cnt += 1;
+    }
+
+    fn inc_filter(&mut self) {
+        self.ft_cnt += 1;
+    }
+
+    fn inc_misfilter(&mut self) {
+        self.mf_cnt += 1;
+    }
+
+    fn inc_lost(&mut self) {
+        self.ls_cnt += 1;
+    }
+
+    fn inc_drop(&mut self) {
+        self.dr_cnt += 1;
+    }
+
+    fn inc_abandon(&mut self) {
+        self.ab_cnt += 1;
+    }
+
+    fn inc_flood(&mut self, missed: usize) {
+        self.fl_cnt += 1;
+        self.ms_cnt += missed;
+    }
+
+    fn report(&mut self) {
+        info!(target: "facility", "Handled {} successes, {} failures ({} answers lost), {} dispatches \
+            ({} misdelivered), {} discards, {} filters ({} misfiltered), {} floods ({} members missed), {} drops, \
+            {} abandons and {} migrations.", self.s_cnt, self.f_cnt, self.ls_cnt, self.dp_cnt, self.md_cnt,
+            self.dc_cnt, self.ft_cnt, self.mf_cnt, self.fl_cnt, self.ms_cnt, self.dr_cnt, self.ab_cnt, self.mg_cnt);
+        self.s_cnt = 0;
+        self.f_cnt = 0;
+        self.dp_cnt = 0;
+        self.dc_cnt = 0;
+        self.md_cnt = 0;
+        self.mg_cnt = 0;
+        self.fl_cnt = 0;
+        self.ms_cnt = 0;
+        self.ft_cnt = 0;
+        self.mf_cnt = 0;
+        self.dr_cnt = 0;
+        self.ls_cnt = 0;
+        self.ab_cnt = 0;
+    }
+}
+
+/// Statistics of frames dispatched to a segment where their destination is not located.
+///
+/// Times are nanoseconds since the beginning of simulation.
+pub struct MisdeliveryStat {
+    pub records: Vec<Option<u64>>,
+    pub times: Vec<u64>,
+}
+
+impl MisdeliveryStat {
+    fn new() -> Self {
+        MisdeliveryStat { records: Vec::new(), times: Vec::new() }
+    }
+
+    /// Record a misdelivery, with the time its destination last migrated if it ever did.
+    fn rec(&mut self, migrated: Option<u64>, now: u64) {
+        self.records.push(migrated);
+        self.times.push(now);
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Export scatter of misdeliveries, with time elapsed since migration of the destination
+    /// (i.e. how stale the bridge is), or -1 if the destination never migrated.
+    fn export_misdelivery_scatter(&self, scenario: &Scenario) {
+        let sc_misdelivery = self.records.iter()
+            .zip(self.times.iter())
+            .map(|(x, y)| {
+                let stale = x.map(|x| ((y - x) / 1000) as i64).unwrap_or(-1);
+                vec![(y / 1000) as i64, stale]
+            })
+            .collect::<Vec<_>>();
+        serde_pickle::to_writer(&mut BufWriter::new(File::create(scenario.out_path("sc_misdelivery.pkl")).unwrap()),
+                                &sc_misdelivery, SerOptions::default()).unwrap();
+    }
+}
+
+/// Segments attached to the bridge, with stations located on them.
+pub struct Facility {
+    scenario: Scenario,
+    count: usize,
+    cur_n: usize,
+    mapping: BTreeMap<Address, Segment>,
+    migrated: BTreeMap<Address, u64>,
+    /// Members of multicast groups as reported.
+    groups: BTreeMap<Address, BTreeSet<Address>>,
+    meter: FacilityMeter,
+    /// Queue of commands arriving at the facility.
+    egress: Queue,
+    /// Frames dropped at the ingress queue of the bridge.
+    ingress_dropped: usize,
+    /// Frames held by the bridge and given up.
+    abandoned: usize,
+    /// Randomness of delays and losses of answers to broadcasts.
+    rng: fastrand::Rng,
+    lost_total: usize,
+    ft_total: usize,
+    mf_total: usize,
+    fl_total: usize,
+    copy_total: usize,
+    missed_total: usize,
+    md_stat: MisdeliveryStat,
+    /// Output ports of the bridge onto segments.
+    links: BTreeMap<Segment, Link>,
+    link_stat: LinkStat,
+    /// Time of the last command handled.
+    now: u64,
+    last_t: u64,
+}
+
+impl Facility {
+    /// Create the facility expecting specified count of frames to be dispatched or discarded.
+    pub fn new(scenario: &Scenario, count: usize, mapping: BTreeMap<Address, Segment>) -> Self {
+        Facility {
+            scenario: scenario.clone(),
+            count,
+            cur_n: 0,
+            mapping,
+            migrated: BTreeMap::new(),
+            groups: BTreeMap::new(),
+            meter: FacilityMeter::new(),
+            egress: Queue::new(scenario.egress_capacity, scenario.queue_policy.clone(), scenario.seed.wrapping_add(1)),
+            ingress_dropped: 0,
+            abandoned: 0,
+            rng: fastrand::Rng::with_seed(scenario.seed.wrapping_add(2)),
+            lost_total: 0,
+            ft_total: 0,
+            mf_total: 0,
+            fl_total: 0,
+            copy_total: 0,
+            missed_total: 0,
+            md_stat: MisdeliveryStat::new(),
+            links: scenario.links(),
+            link_stat: LinkStat::new(),
+            now: 0,
+            last_t: 0,
+        }
+    }
+
+    /// Whether all frames are dispatched, discarded, filtered, flooded or dropped.
+    pub fn finished(&self) -> bool {
+        self.cur_n == self.count
+    }
+
+    /// Offer a command arriving at specified time to the egress queue holding a count of commands.
+    ///
+    /// Returns the command if admitted. A dropped frame counts as handled, and the bridge is told
+    /// to shut down if it is the last one.
+    pub fn enqueue(&mut self, command: Command, depth: usize, now: u64, te: &mut impl FnMut(u64, Event))
+                   -> Option<Command> {
+        let priority = match &command {
+            Command::Dispatch(frame, ..) | Command::Flood(frame, ..) => Some(queue::priority(frame)),
+            _ => None,
+        };
+        if self.egress.admit(depth, priority, now) {
+            return Some(command);
+        }
+        self.meter.inc_drop();
+        self.cur_n += 1;
+        if self.finished() {
+            te(now, Event::Shutdown);
+        }
+        None
+    }
+
+    /// Send a frame received by the bridge at some time on a segment, through its output queue.
+    fn transmit(&mut self, frame: &Frame, segment: Segment, received: u64, now: u64) {
+        let link = self.links.entry(segment).or_insert_with(|| self.scenario.default_link());
+        let delays = link.send(frame.wire_len(), now)
+            .map(|tx| (tx.queueing, tx.delivered.saturating_sub(received)));
+        self.link_stat.rec(segment, delays, now);
+    }
+
+    /// Handle a command at specified time, emitting events to the bridge with the time they arrive.
+    ///
+    /// Returns `false` if all frames are handled and the bridge is told to shut down.
+    pub fn handle(&mut self, command: Command, now: u64, te: &mut impl FnMut(u64, Event)) -> bool {
+        match command {
+            Command::Broadcast(addr) => {
+                let due = now + self.scenario.reply_delay.sample(self.scenario.broadcast_delay_ns, &mut self.rng);
+                if self.rng.f64() < self.scenario.reply_loss {
+                    // never heard of by the bridge
+                    self.meter.inc_lost();
+                    self.lost_total += 1;
+                } else if let Some(segment) = self.mapping.get(&addr) {
+                    te(due, Event::Success(addr, *segment));
+                    self.meter.inc_success();
+                } else {
+                    te(due, Event::Failure(addr));
+                    self.meter.inc_failure();
+                }
+            }
+            Command::Dispatch(frame, segment, received) => {
+                trace!(target: "facility", "Dispatched {} to {}.", frame, segment);
+                self.transmit(&frame, segment, received, now);
+                if self.mapping.get(&frame.dst) != Some(&segment) {
+                    // the destination is not there, or has moved away
+                    self.md_stat.rec(self.migrated.get(&frame.dst).copied(), now);
+                    self.meter.inc_misdelivery();
+                }
+                self.meter.inc_dispatch();
+                self.cur_n += 1;
+            }
+            Command::Discard(frame) => {
+                trace!(target: "facility", "Discarded {}.", frame);
+                self.meter.inc_discard();
+                self.cur_n += 1;
+            }
+            Command::Filter(frame) => {
+                trace!(target: "facility", "Filtered {}.", frame);
+                if self.mapping.get(&frame.dst) != Some(&frame.src_seg) {
+                    // the destination has moved away and never receives the frame
+                    self.meter.inc_misfilter();
+                    self.mf_total += 1;
+                }
+                self.meter.inc_filter();
+                self.ft_total += 1;
+                self.cur_n += 1;
+            }
+            Command::Migrate(addr, segment) => {
+                trace!(target: "facility", "Moved {} to {}.", addr, segment);
+                self.mapping.insert(addr, segment);
+                self.migrated.insert(addr, now);
+                self.meter.inc_migration();
+            }
+            Command::Flood(frame, segments, received) => {
+                trace!(target: "facility", "Flooded {} to {} segments.", frame, segments.len());
+                for segment in &segments {
+                    self.transmit(&frame, *segment, received, now);
+                }
+                // members of the group located on none of the segments never receive the frame
+                let missed = match frame.dst.is_broadcast() {
+                    true => 0,
+                    false => self.groups.get(&frame.dst).map(|members| members.iter()
+                        .filter_map(|member| self.mapping.get(member))
+                        .filter(|segment| **segment != frame.src_seg && !segments.contains(segment))
+                        .count()).unwrap_or(0),
+                };
+                self.meter.inc_flood(missed);
+                self.fl_total += 1;
+                self.copy_total += segments.len();
+                self.missed_total += missed;
+                self.cur_n += 1;
+            }
+            Command::Drop(frame) => {
+                trace!(target: "facility", "Dropped {} at ingress.", frame);
+                self.meter.inc_drop();
+                self.ingress_dropped += 1;
+                self.cur_n += 1;
+            }
+            Command::Abandon(frame) => {
+                trace!(target: "facility", "Gave up {} held.", frame);
+                self.meter.inc_abandon();
+                self.abandoned += 1;
+                self.cur_n += 1;
+            }
+            Command::Report(membership) => {
+                trace!(target: "facility", "Reported {}.", membership);
+                if membership.join {
+                    self.groups.entry(membership.group).or_default().insert(membership.addr);
+                } else if let Some(members) = self.groups.get_mut(&membership.group) {
+                    members.remove(&membership.addr);
+                    if members.is_empty() {
+                        self.groups.remove(&membership.group);
+                    }
+                }
+            }
+        }
+        self.now = now;
+        if now - self.last_t > self.scenario.facility_log_ms * 1_000_000 {
+            self.meter.report();
+            self.last_t = now;
+        }
+        if self.finished() {
+            te(now, Event::Shutdown);
+            return false;
+        }
+        true
+    }
+
+    /// Export statistics.
+    pub fn export(&self) {
+        info!(target: "facility", "Recorded {} misdeliveries.", self.md_stat.len());
+        info!(target: "facility", "Received {} filters, {} of them to stations moved away.", self.ft_total,
+            self.mf_total);
+        info!(target: "facility", "Received {} floods in {} copies, missing {} members of groups.", self.fl_total,
+            self.copy_total, self.missed_total);
+        info!(target: "facility", "Dropped {} frames at ingress and {} at egress (capacity {}), {} held by the \
+            bridge.", self.ingress_dropped, self.egress.dropped(), self.egress.capacity(), self.abandoned);
+        info!(target: "facility", "Lost {} answers to broadcasts.", self.lost_total);
+        let (queueing, latency) = self.link_stat.mean_delays();
+        let dropped = self.links.values().map(Link::dropped).sum::<usize>();
+        let busiest = self.links.iter()
+            .map(|(segment, link)| (link.utilisation(self.now), segment))
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+        info!(target: "facility", "Sent {} frames on {} segments, {} of them dropped at output queues. Mean \
+            queueing delay {:.0} ns and latency {:.0} ns.", self.link_stat.len(), self.links.len(), dropped,
+            queueing, latency);
+        if let Some((utilisation, segment)) = busiest {
+            info!(target: "facility", "Busiest segment {} at utilisation {:.4}.", segment, utilisation);
+        }
+        self.md_stat.export_misdelivery_scatter(&self.scenario);
+        self.egress.export(&self.scenario, "egress");
+        self.link_stat.export_link_delay_scatter(&self.scenario);
+        self.export_link_utilisation();
+    }
+
+    /// Export utilisation of segments, with frames sent, frames dropped and time spent serializing.
+    fn export_link_utilisation(&self) {
+        let sc_link_utilisation = self.links.iter()
+            .map(|(segment, link)| {
+                (u16::from_be_bytes(segment.data) as i64, link.sent() as i64, link.dropped() as i64,
+                 link.busy() as i64, link.utilisation(self.now))
+            })
+            .collect::<Vec<_>>();
+        let path = self.scenario.out_path("sc_link_utilisation.pkl");
+        serde_pickle::to_writer(&mut BufWriter::new(File::create(path).unwrap()), &sc_link_utilisation,
+                                SerOptions::default()).unwrap();
+    }
+}
+
+/// Events to the bridge not yet due, sent in order of time.
+struct Replies {
+    events: BTreeMap<(u64, u64), Event>,
+    seq: u64,
+}
+
+impl Replies {
+    fn new() -> Self {
+        Replies { events: BTreeMap::new(), seq: 0 }
+    }
+
+    /// Send the events due at specified time, as long as the bridge has room for them.
+    fn flush(&mut self, now: u64, te: &Inlet<Event>) {
+        while let Some(entry) = self.events.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            let (key, event) = entry.remove_entry();
+            match te.try_send(event) {
+                Ok(()) => {}
+                Err(TrySendError::Full(event)) => {
+                    self.events.insert(key, event);
+                    break;
+                }
+                // the bridge is gone, nobody to tell
+                Err(TrySendError::Disconnected(_)) => self.events.clear(),
+            }
+        }
+    }
+
+    /// Send an event if due at specified time, or keep it until it is and the bridge has room.
+    ///
+    /// Events not yet due are forgotten once the bridge is told to shut down.
+    fn emit(&mut self, due: u64, event: Event, now: u64, te: &Inlet<Event>) {
+        if matches!(event, Event::Shutdown) {
+            self.flush(now, te);
+            self.events.clear();
+        }
+        self.events.insert((due, self.seq), event);
+        self.seq += 1;
+        self.flush(now, te);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Earliest time an event is due.
+    fn next_due(&self) -> Option<u64> {
+        self.events.first_key_value().map(|((due, _), _)| *due)
+    }
+}
+
+/// Time to wait before sending again to the bridge when it has no room.
+const RETRY: Duration = Duration::from_micros(10);
+
+/// Facilitation service that handle commands from the bridge.
+///
+/// Events to the bridge never wait for room, as the bridge may itself be waiting for room in the
+/// egress queue; they are kept until sent instead.
+pub fn facility(origin: Instant, mut facility: Facility, te: Inlet<Event>, rc: Receiver<Command>) {
+    info!(target: "facility", "Facility started.");
+    let mut replies = Replies::new();
+    let mut pending = VecDeque::new();
+    loop {
+        // receive a command, those arrived while busy waiting in the egress queue, until an answer is due
+        let wait = replies.next_due()
+            .map(|due| Duration::from_nanos(due.saturating_sub(elapsed(origin))).max(RETRY));
+        let command = queue::receive(&rc, &mut pending, wait, |command, depth| {
+            facility.enqueue(command, depth, elapsed(origin),
+                             &mut |due, event| replies.emit(due, event, elapsed(origin), &te))
+        });
+        replies.flush(elapsed(origin), &te);
+        match command {
+            // the bridge is already told to shut down if the last frame was dropped
+            Ok(command) => {
+                let mut emit = |due, event| replies.emit(due, event, elapsed(origin), &te);
+                if facility.finished() || !facility.handle(command, elapsed(origin), &mut emit) {
+                    break;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    // keep the bridge going until it is told to shut down
+    while !replies.is_empty() {
+        thread::sleep(RETRY);
+        while rc.try_recv().is_ok() {}
+        replies.flush(elapsed(origin), &te);
+    }
+    facility.export();
+    info!(target: "facility", "Facility exiting.");
+}
@@ -0,0 +1,139 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufWriter;
+use serde_pickle::SerOptions;
+use net_exp_bridge::Segment;
+use crate::scenario::Scenario;
+
+/// Count of bytes sent along with every frame: preamble, start frame delimiter and interframe gap.
+const OVERHEAD: usize = 20;
+
+/// Frame sent on a link, with times in nanoseconds since the beginning of simulation.
+#[derive(Debug, Copy, Clone)]
+pub struct Transmission {
+    /// Time the frame waited in the output queue before being serialized.
+    pub queueing: u64,
+    /// Time the last bit of the frame reaches the other end of the segment.
+    pub delivered: u64,
+}
+
+/// Output port of the bridge onto a segment, serializing frames one after another at the bit rate
+/// of the segment.
+pub struct Link {
+    bitrate_bps: u64,
+    delay_ns: u64,
+    /// Maximum count of frames waiting, or 0 for no limit.
+    capacity: usize,
+    /// Times when frames waiting begin to be serialized.
+    waiting: VecDeque<u64>,
+    busy_until: u64,
+    /// Time spent serializing.
+    busy: u64,
+    sent: usize,
+    dropped: usize,
+}
+
+impl Link {
+    pub fn new(bitrate_bps: u64, delay_ns: u64, capacity: usize) -> Self {
+        Link {
+            bitrate_bps: bitrate_bps.max(1),
+            delay_ns,
+            capacity,
+            waiting: VecDeque::new(),
+            busy_until: 0,
+            busy: 0,
+            sent: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Time to serialize a count of bytes of a frame, overhead included.
+    pub fn serialization_ns(&self, bytes: usize) -> u64 {
+        ((bytes + OVERHEAD) as u64 * 8 * 1_000_000_000).div_ceil(self.bitrate_bps)
+    }
+
+    /// Send a frame of a count of bytes at specified time, after those waiting.
+    ///
+    /// Returns `None` if the frame is dropped for the output queue being full.
+    pub fn send(&mut self, bytes: usize, now: u64) -> Option<Transmission> {
+        while self.waiting.front().is_some_and(|begin| *begin <= now) {
+            self.waiting.pop_front();
+        }
+        if self.capacity > 0 && self.waiting.len() >= self.capacity {
+            self.dropped += 1;
+            return None;
+        }
+        let begin = self.busy_until.max(now);
+        let serialization = self.serialization_ns(bytes);
+        self.waiting.push_back(begin);
+        self.busy_until = begin + serialization;
+        self.busy += serialization;
+        self.sent += 1;
+        Some(Transmission { queueing: begin - now, delivered: self.busy_until + self.delay_ns })
+    }
+
+    /// Share of time spent serializing frames until specified time.
+    pub fn utilisation(&self, now: u64) -> f64 {
+        self.busy as f64 / now.max(self.busy_until).max(1) as f64
+    }
+
+    pub fn sent(&self) -> usize {
+        self.sent
+    }
+
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    pub fn busy(&self) -> u64 {
+        self.busy
+    }
+}
+
+/// Statistics of frames sent on segments.
+///
+/// Times are nanoseconds since the beginning of simulation.
+pub struct LinkStat {
+    /// Segment, queueing delay and latency since the bridge received the frame, or `None` if the
+    /// frame is dropped at the output queue.
+    pub records: Vec<(Segment, Option<(u64, u64)>)>,
+    pub times: Vec<u64>,
+}
+
+impl LinkStat {
+    pub fn new() -> Self {
+        LinkStat { records: Vec::new(), times: Vec::new() }
+    }
+
+    pub fn rec(&mut self, segment: Segment, delays: Option<(u64, u64)>, now: u64) {
+        self.records.push((segment, delays));
+        self.times.push(now);
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Mean queueing delay and latency of frames delivered, in nanoseconds.
+    pub fn mean_delays(&self) -> (f64, f64) {
+        let delivered = self.records.iter().filter_map(|(_, delays)| *delays).collect::<Vec<_>>();
+        let n = delivered.len().max(1) as f64;
+        let queueing = delivered.iter().map(|(q, _)| *q as f64).sum::<f64>();
+        let latency = delivered.iter().map(|(_, l)| *l as f64).sum::<f64>();
+        (queueing / n, latency / n)
+    }
+
+    /// Export scatter of delays of frames on segments, with queueing delay and latency in
+    /// nanoseconds, or -1 for both if the frame is dropped.
+    pub fn export_link_delay_scatter(&self, scenario: &Scenario) {
+        let sc_link_delay = self.records.iter()
+            .zip(self.times.iter())
+            .map(|((segment, delays), t)| {
+                let (queueing, latency) = delays.map(|(q, l)| (q as i64, l as i64)).unwrap_or((-1, -1));
+                vec![(t / 1000) as i64, u16::from_be_bytes(segment.data) as i64, queueing, latency]
+            })
+            .collect::<Vec<_>>();
+        serde_pickle::to_writer(&mut BufWriter::new(File::create(scenario.out_path("sc_link_delay.pkl")).unwrap()),
+                                &sc_link_delay, SerOptions::default()).unwrap();
+    }
+}
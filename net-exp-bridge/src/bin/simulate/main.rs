@@ -0,0 +1,356 @@
+mod arrival;
+mod bridge;
+mod des;
+mod facility;
+mod link;
+mod network;
+mod orchestrator;
+mod queue;
+mod scenario;
+mod topology;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Instant;
+use clap::Parser;
+use net_exp_bridge::{trace, Address, Frame, Membership, Migration, ParseError, Segment};
+use net_exp_bridge::fdb::EvictionPolicy;
+use net_exp_bridge::stream::FrameReader;
+use crate::bridge::{bridge, Bridge, HoldOverflow};
+use crate::facility::{facility, Facility, ReplyDelay};
+use crate::arrival::Distribution;
+use crate::orchestrator::orchestrator;
+use crate::queue::{Inlet, QueuePolicy};
+use crate::scenario::Scenario;
+use crate::topology::{SpanningTree, Topology};
+
+/// Simulate a learning bridge with generated frames.
+///
+/// Options given on command line override those of the scenario file.
+#[derive(Parser)]
+struct Args {
+    /// Scenario file in TOML, or JSON if ending with `.json`
+    #[arg(long)]
+    scenario: Option<PathBuf>,
+    /// Run on a virtual clock with discrete events instead of threads on wall clock
+    #[arg(long)]
+    discrete: bool,
+    /// Seed of randomness in simulation
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Directory to read generated files from
+    #[arg(long)]
+    in_dir: Option<PathBuf>,
+    /// Prefix of names of generated files
+    #[arg(long)]
+    in_prefix: Option<String>,
+    /// Text trace of frames to send instead of the generated ones
+    #[arg(long)]
+    frame_trace: Option<PathBuf>,
+    /// Directory to write statistics in
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+    /// Prefix of names of statistics files
+    #[arg(long)]
+    out_prefix: Option<String>,
+    /// Duration in which frames are sent, in seconds
+    #[arg(long)]
+    elapse_sec: Option<usize>,
+    /// Model of arrival of frames with default parameters (uniform, half_circle, poisson, mmpp,
+    /// diurnal, step, ramp, pareto), or `trace:<path>` to replay a trace of timestamps
+    #[arg(long, value_parser = |s: &str| s.parse::<Distribution>())]
+    distribution: Option<Distribution>,
+    /// Time after which a learned address is forgotten
+    #[arg(long)]
+    aging_ms: Option<u64>,
+    /// Maximum count of entries in the forwarding table
+    #[arg(long)]
+    table_capacity: Option<usize>,
+    /// Strategy to make room in the forwarding table (lru, fifo, random, lfu)
+    #[arg(long, value_parser = |s: &str| EvictionPolicy::try_from(s))]
+    eviction_policy: Option<EvictionPolicy>,
+    /// Flood frames to multicast groups to all segments instead of those their members reported from
+    #[arg(long)]
+    no_igmp_snooping: bool,
+    /// Distribution of time for the segments to answer a broadcast around its mean (constant, uniform,
+    /// exponential, log_normal)
+    #[arg(long, value_parser = |s: &str| s.parse::<ReplyDelay>())]
+    reply_delay: Option<ReplyDelay>,
+    /// Probability that the answer to a broadcast is lost
+    #[arg(long)]
+    reply_loss: Option<f64>,
+    /// Time the bridge waits for an answer to a broadcast before broadcasting again, 0 to wait for ever
+    #[arg(long)]
+    broadcast_timeout_ns: Option<u64>,
+    /// Count of broadcasts sent again for an address before the bridge gives up and discards its frames
+    #[arg(long)]
+    broadcast_retries: Option<u32>,
+    /// Time a frame is held for the segment of its destination before it is given up, 0 for no limit
+    #[arg(long)]
+    hold_timeout_ns: Option<u64>,
+    /// Maximum count of frames held for a destination, 0 for no limit
+    #[arg(long)]
+    hold_per_addr: Option<usize>,
+    /// Maximum count of frames held for all destinations, 0 for no limit
+    #[arg(long)]
+    hold_capacity: Option<usize>,
+    /// Frame given up when frames held reach a limit (drop_oldest, drop_newest)
+    #[arg(long, value_parser = |s: &str| s.parse::<HoldOverflow>())]
+    hold_overflow: Option<HoldOverflow>,
+    /// Bit rate of segments attached to the bridge, unless told per segment
+    #[arg(long)]
+    link_bitrate_bps: Option<u64>,
+    /// Maximum count of frames waiting to be sent on a segment, or 0 for no limit, unless told per segment
+    #[arg(long)]
+    link_queue_capacity: Option<usize>,
+    /// Maximum count of events waiting for the bridge, or 0 for no limit
+    #[arg(long)]
+    ingress_capacity: Option<usize>,
+    /// Maximum count of commands waiting for the facility, or 0 for no limit
+    #[arg(long)]
+    egress_capacity: Option<usize>,
+    /// Policy to drop frames at a full or congested queue with default parameters (tail_drop, red, priority)
+    #[arg(long, value_parser = |s: &str| s.parse::<QueuePolicy>())]
+    queue_policy: Option<QueuePolicy>,
+    /// Topology file of a network of bridges to simulate instead of one bridge (always discrete)
+    #[arg(long)]
+    topology: Option<PathBuf>,
+    /// Spanning tree protocol of bridges in the network unless the topology tells (none, stp, rstp)
+    #[arg(long, value_parser = |s: &str| s.parse::<SpanningTree>())]
+    spanning_tree: Option<SpanningTree>,
+    /// Simulate the network with classic then rapid spanning tree on all bridges, and compare
+    #[arg(long)]
+    compare_spanning_tree: bool,
+}
+
+impl Args {
+    /// Resolve the scenario from file and command line.
+    fn scenario(self) -> Scenario {
+        let mut scenario = self.scenario.as_deref().map(Scenario::load).unwrap_or_default();
+        scenario.discrete |= self.discrete;
+        scenario.seed = self.seed.unwrap_or(scenario.seed);
+        scenario.in_dir = self.in_dir.unwrap_or(scenario.in_dir);
+        scenario.in_prefix = self.in_prefix.unwrap_or(scenario.in_prefix);
+        scenario.frame_trace = self.frame_trace.or(scenario.frame_trace);
+        scenario.out_dir = self.out_dir.unwrap_or(scenario.out_dir);
+        scenario.out_prefix = self.out_prefix.unwrap_or(scenario.out_prefix);
+        scenario.elapse_sec = self.elapse_sec.unwrap_or(scenario.elapse_sec);
+        scenario.distribution = self.distribution.unwrap_or(scenario.distribution);
+        scenario.aging_ms = self.aging_ms.unwrap_or(scenario.aging_ms);
+        scenario.table_capacity = self.table_capacity.unwrap_or(scenario.table_capacity);
+        scenario.eviction_policy = self.eviction_policy.unwrap_or(scenario.eviction_policy);
+        scenario.igmp_snooping &= !self.no_igmp_snooping;
+        scenario.reply_delay = self.reply_delay.unwrap_or(scenario.reply_delay);
+        scenario.reply_loss = self.reply_loss.unwrap_or(scenario.reply_loss);
+        scenario.broadcast_timeout_ns = self.broadcast_timeout_ns.unwrap_or(scenario.broadcast_timeout_ns);
+        scenario.broadcast_retries = self.broadcast_retries.unwrap_or(scenario.broadcast_retries);
+        scenario.hold_timeout_ns = self.hold_timeout_ns.unwrap_or(scenario.hold_timeout_ns);
+        scenario.hold_per_addr = self.hold_per_addr.unwrap_or(scenario.hold_per_addr);
+        scenario.hold_capacity = self.hold_capacity.unwrap_or(scenario.hold_capacity);
+        scenario.hold_overflow = self.hold_overflow.unwrap_or(scenario.hold_overflow);
+        scenario.link_bitrate_bps = self.link_bitrate_bps.unwrap_or(scenario.link_bitrate_bps);
+        scenario.link_queue_capacity = self.link_queue_capacity.unwrap_or(scenario.link_queue_capacity);
+        scenario.ingress_capacity = self.ingress_capacity.unwrap_or(scenario.ingress_capacity);
+        scenario.egress_capacity = self.egress_capacity.unwrap_or(scenario.egress_capacity);
+        scenario.queue_policy = self.queue_policy.unwrap_or(scenario.queue_policy);
+        scenario.topology = self.topology.or(scenario.topology);
+        scenario.spanning_tree = self.spanning_tree.unwrap_or(scenario.spanning_tree);
+        scenario.compare_spanning_tree |= self.compare_spanning_tree;
+        scenario
+    }
+}
+
+/// Event that bridge receives.
+enum Event {
+    /// Incoming request of routing a frame.
+    Request(Frame),
+    /// Found segment that accept an address.
+    Success(Address, Segment),
+    /// No segment accepts an address.
+    Failure(Address),
+    /// A station moved to another segment, relayed to the facility in order with the frames.
+    Migrate(Address, Segment),
+    /// A station joined or left a multicast group, as told by an IGMP report from its segment.
+    Report(Membership),
+    /// Simulation finishing and the bridge should be exiting.
+    Shutdown,
+}
+
+/// Command that bridge emits.
+enum Command {
+    /// Broadcast an address to segments
+    Broadcast(Address),
+    /// Dispatch a frame received at some time to a segment
+    Dispatch(Frame, Segment, u64),
+    /// Discard a frame
+    Discard(Frame),
+    /// Filter a frame whose destination is on the segment it comes from
+    Filter(Frame),
+    /// Move a station to another segment (relayed untouched)
+    Migrate(Address, Segment),
+    /// Flood a frame to a broadcast or multicast address, received at some time, to segments
+    Flood(Frame, Vec<Segment>, u64),
+    /// Change membership of a station in a group (relayed untouched)
+    Report(Membership),
+    /// Account for a frame dropped at the ingress queue of the bridge
+    Drop(Frame),
+    /// Account for a frame held by the bridge and given up
+    Abandon(Frame),
+}
+
+/// Nanoseconds elapsed since the beginning of simulation.
+fn elapsed(origin: Instant) -> u64 {
+    origin.elapsed().as_nanos() as u64
+}
+
+/// Read all records of a text trace, parsing each line with a function.
+fn read_trace<T>(path: &Path, parse: impl Fn(&str) -> Result<T, ParseError>) -> Vec<T> {
+    let file = File::open(path).unwrap_or_else(|err| panic!("cannot read trace {}: {}", path.display(), err));
+    trace::read_lines(BufReader::new(file), parse)
+        .collect::<Result<_, _>>()
+        .unwrap_or_else(|err| panic!("invalid trace {}: {}", path.display(), err))
+}
+
+/// Load segment mapping from disk, from `addr_seg.txt` if the dataset is written by hand.
+fn load_mapping(scenario: &Scenario) -> BTreeMap<Address, Segment> {
+    let addr_seg: Vec<(Address, Segment)> = match File::open(scenario.in_path("addr_seg.rmp")) {
+        Ok(addr_seg) => rmp_serde::from_read(BufReader::new(addr_seg)).unwrap(),
+        Err(_) => read_trace(&scenario.in_path("addr_seg.txt"), trace::parse_location),
+    };
+    BTreeMap::from_iter(addr_seg)
+}
+
+/// Load scheduled migrations from disk, from `migration.txt` if the dataset is written by hand, or
+/// none if the dataset has no migrations.
+fn load_migrations(scenario: &Scenario) -> Vec<Migration> {
+    if let Ok(migration) = File::open(scenario.in_path("migration.rmp")) {
+        return rmp_serde::from_read(BufReader::new(migration)).unwrap();
+    }
+    let path = scenario.in_path("migration.txt");
+    if path.exists() { read_trace(&path, str::parse) } else { Vec::new() }
+}
+
+/// Load scheduled memberships in multicast groups from disk, from `membership.txt` if the dataset is
+/// written by hand, or none if the dataset has none.
+fn load_memberships(scenario: &Scenario) -> Vec<Membership> {
+    if let Ok(membership) = File::open(scenario.in_path("membership.rmp")) {
+        return rmp_serde::from_read(BufReader::new(membership)).unwrap();
+    }
+    let path = scenario.in_path("membership.txt");
+    if path.exists() { read_trace(&path, str::parse) } else { Vec::new() }
+}
+
+/// Segments attached to the bridge, i.e. where stations are located at some point.
+fn attached_segments(mapping: &BTreeMap<Address, Segment>, migrations: &[Migration]) -> Vec<Segment> {
+    let segments = mapping.values()
+        .copied()
+        .chain(migrations.iter().map(|m| m.seg))
+        .collect::<BTreeSet<_>>();
+    segments.into_iter().collect()
+}
+
+/// Frames to send in order, read from disk one by one as they are sent if the dataset has a frame
+/// file, or held in memory otherwise.
+pub struct Frames {
+    count: usize,
+    frames: Box<dyn Iterator<Item = Frame> + Send>,
+}
+
+impl Frames {
+    /// Count of frames in all, sent or not.
+    fn len(&self) -> usize {
+        self.count
+    }
+}
+
+impl From<Vec<Frame>> for Frames {
+    fn from(frames: Vec<Frame>) -> Self {
+        Frames { count: frames.len(), frames: Box::new(frames.into_iter()) }
+    }
+}
+
+impl Iterator for Frames {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        self.frames.next()
+    }
+}
+
+/// Load frames from the trace of the scenario if any, or generated frames from disk, from
+/// `frame.rmp` of old datasets or `frame.txt` if the dataset is written by hand.
+fn load_frames(scenario: &Scenario) -> Frames {
+    if let Some(path) = &scenario.frame_trace {
+        return Frames::from(read_trace(path, str::parse));
+    }
+    let path = scenario.in_path("frame.bin");
+    if let Ok(frame) = File::open(&path) {
+        let reader = FrameReader::new(BufReader::new(frame))
+            .unwrap_or_else(|err| panic!("invalid frame file {}: {}", path.display(), err));
+        let count = reader.frame_cnt();
+        let frames = reader.map(move |frame| {
+            frame.unwrap_or_else(|err| panic!("invalid frame file {}: {}", path.display(), err))
+        });
+        return Frames { count, frames: Box::new(frames) };
+    }
+    match File::open(scenario.in_path("frame.rmp")) {
+        Ok(frame) => Frames::from(rmp_serde::from_read::<_, Vec<Frame>>(BufReader::new(frame)).unwrap()),
+        Err(_) => Frames::from(read_trace(&scenario.in_path("frame.txt"), str::parse)),
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let scenario = Args::parse().scenario();
+    scenario.validate();
+    let migrations = load_migrations(&scenario);
+    let memberships = load_memberships(&scenario);
+    let mapping = load_mapping(&scenario);
+    fs::create_dir_all(&scenario.out_dir).unwrap();
+    if let Some(path) = &scenario.topology {
+        if scenario.compare_spanning_tree {
+            network::compare(&scenario, &Topology::load(path), migrations, memberships, mapping);
+        } else {
+            network::run(&scenario, &Topology::load(path), load_frames(&scenario), migrations, memberships,
+                         mapping);
+        }
+        return;
+    }
+    let frames = load_frames(&scenario);
+    if scenario.discrete {
+        des::run(&scenario, frames, migrations, memberships, mapping);
+        return;
+    }
+
+    // channels hold as much as the queues they feed
+    let (tc, rc) = Inlet::channel(scenario.egress_capacity);
+    let (te, re) = Inlet::channel(scenario.ingress_capacity);
+    let origin = Instant::now();
+
+    let segments = attached_segments(&mapping, &migrations);
+    let facility = {
+        let facility_state = Facility::new(&scenario, frames.len(), mapping);
+        let te = te.clone();
+        thread::spawn(move || facility(origin, facility_state, te, rc))
+    };
+
+    let bridge = {
+        // the only sender of commands, so that the facility sees the channel closed once the bridge exits
+        let bridge_state = Bridge::new(&scenario, segments);
+        thread::spawn(move || bridge(origin, bridge_state, tc, re))
+    };
+
+    let orchestrator = {
+        let scenario = scenario.clone();
+        let te = te.clone();
+        thread::spawn(move || orchestrator(scenario, frames, migrations, memberships, te))
+    };
+
+    orchestrator.join().unwrap();
+    facility.join().unwrap();
+    bridge.join().unwrap();
+}
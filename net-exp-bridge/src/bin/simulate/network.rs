@@ -0,0 +1,689 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::BufWriter;
+use log::info;
+use serde_pickle::SerOptions;
+use net_exp_bridge::{Address, Frame, Membership, Migration, Segment, VlanTag};
+use net_exp_bridge::fdb::{EvictionPolicy, FdbStats, ForwardingDatabase};
+use net_exp_bridge::stp::{Bpdu, BridgeId, PortRole, PortState, Stp, StpAction, Timers};
+use crate::des::Engine;
+use crate::orchestrator::{distribute, Sequencer};
+use crate::scenario::Scenario;
+use crate::topology::{Network, PortVlan, SpanningTree, Topology};
+use crate::{Event, Frames};
+
+/// Copy of a frame travelling in the network.
+struct Transit {
+    /// Index of the frame in order of sending.
+    id: usize,
+    frame: Frame,
+    /// Count of bridges the copy has passed.
+    hops: u32,
+}
+
+/// Job to be done at some point of simulated time.
+enum Job {
+    /// Orchestrator sends frames of a bucket (one millisecond).
+    Tick(usize),
+    /// Station sends a frame, or moves to another segment.
+    Send(Event),
+    /// Copy of a frame arrives at a port of a bridge.
+    Arrive(usize, usize, Transit),
+    /// BPDU arrives at a port of a bridge.
+    Bpdu(usize, usize, Bpdu),
+    /// Timers of spanning tree are checked.
+    StpTick,
+    /// Segment goes down or comes back up.
+    Link(usize, bool),
+}
+
+/// Counters of a bridge since the beginning of simulation.
+#[derive(Default)]
+struct NetBridgeMeter {
+    received: usize,
+    forwarded: usize,
+    /// Frames to unknown unicast addresses flooded.
+    flooded: usize,
+    /// Frames to the broadcast address flooded.
+    broadcast: usize,
+    /// Frames to multicast groups flooded.
+    multicast: usize,
+    filtered: usize,
+    /// Frames dropped for passing too many bridges.
+    dropped: usize,
+    /// Frames dropped for coming from or going to a port not forwarding.
+    blocked: usize,
+    /// Frames dropped for being of a VLAN the port does not carry.
+    foreign: usize,
+}
+
+/// Bridge in a network, learning ports of addresses from the frames it receives.
+///
+/// Each VLAN has a forwarding table of its own, so learning and flooding stay within it.
+struct NetBridge {
+    vlans: Vec<PortVlan>,
+    fdbs: BTreeMap<u16, ForwardingDatabase<usize>>,
+    capacity: usize,
+    policy: EvictionPolicy,
+    seed: u64,
+    /// Aging time of the tables when the topology is stable, in microseconds.
+    aging: u64,
+    /// Aging time of the tables in use, shorter while the topology changes.
+    table_aging: u64,
+    stp: Option<Stp>,
+    /// Time the bridge finishes what it is handling.
+    free: u64,
+    last_sweep: u64,
+    meter: NetBridgeMeter,
+}
+
+impl NetBridge {
+    fn new(scenario: &Scenario, vlans: Vec<PortVlan>, stp: Option<Stp>, seed: u64) -> Self {
+        NetBridge {
+            vlans,
+            fdbs: BTreeMap::new(),
+            capacity: scenario.table_capacity,
+            policy: scenario.eviction_policy,
+            seed,
+            aging: scenario.aging_ms * 1000,
+            table_aging: scenario.aging_ms * 1000,
+            stp,
+            free: 0,
+            last_sweep: 0,
+            meter: NetBridgeMeter::default(),
+        }
+    }
+
+    fn port_cnt(&self) -> usize {
+        self.vlans.len()
+    }
+
+    /// Forwarding table of a VLAN, created when first needed.
+    fn fdb(&mut self, vid: u16) -> &mut ForwardingDatabase<usize> {
+        let (capacity, aging, policy) = (self.capacity, self.table_aging, self.policy);
+        let seed = self.seed.wrapping_add(vid as u64);
+        self.fdbs.entry(vid)
+            .or_insert_with(|| ForwardingDatabase::new(capacity, aging, policy).with_seed(seed))
+    }
+
+    /// Forget addresses learned on a port in all VLANs.
+    fn forget(&mut self, port: usize) {
+        self.fdbs.values_mut().for_each(|fdb| {
+            fdb.forget(port);
+        });
+    }
+
+    fn set_aging(&mut self, aging: u64) {
+        self.table_aging = aging;
+        self.fdbs.values_mut().for_each(|fdb| fdb.set_aging(aging));
+    }
+
+    /// Counters of the tables of all VLANs added up.
+    fn stats(&self) -> FdbStats {
+        self.fdbs.values().map(|fdb| fdb.stats()).fold(FdbStats::default(), |acc, s| FdbStats {
+            hits: acc.hits + s.hits,
+            misses: acc.misses + s.misses,
+            evicted: acc.evicted + s.evicted,
+            aged: acc.aged + s.aged,
+        })
+    }
+
+    fn learning(&self, port: usize) -> bool {
+        self.stp.as_ref().is_none_or(|stp| stp.learning(port))
+    }
+
+    fn forwarding(&self, port: usize) -> bool {
+        self.stp.as_ref().is_none_or(|stp| stp.forwarding(port))
+    }
+
+    /// Handle a frame received on a port at specified time, emitting ports to send it out of with
+    /// the tag it should carry there.
+    fn receive(&mut self, scenario: &Scenario, frame: &Frame, port: usize, now: u64,
+               out: &mut impl FnMut(usize, Option<VlanTag>)) {
+        let now_us = now / 1000;
+        self.meter.received += 1;
+        let Some(vid) = self.vlans[port].ingress(frame.vlan) else {
+            self.meter.foreign += 1;
+            return;
+        };
+        let pcp = frame.vlan.map(|tag| tag.pcp).unwrap_or(0);
+        if self.learning(port) {
+            self.fdb(vid).learn(frame.src, port, now_us);
+        }
+        if !self.forwarding(port) {
+            self.meter.blocked += 1;
+            return;
+        }
+        match self.fdb(vid).lookup(&frame.dst, now_us) {
+            Some(dst_port) if dst_port == port => {
+                // the destination is on the segment the frame comes from
+                self.meter.filtered += 1;
+            }
+            Some(dst_port) if self.forwarding(dst_port) => {
+                self.meter.forwarded += 1;
+                out(dst_port, self.vlans[dst_port].egress(vid, pcp));
+            }
+            Some(_) => {
+                self.meter.blocked += 1;
+            }
+            None => {
+                match frame.dst {
+                    dst if dst.is_broadcast() => self.meter.broadcast += 1,
+                    dst if dst.is_multicast() => self.meter.multicast += 1,
+                    _ => self.meter.flooded += 1,
+                }
+                for p in 0..self.port_cnt() {
+                    if p != port && self.forwarding(p) && self.vlans[p].carries(vid) {
+                        out(p, self.vlans[p].egress(vid, pcp));
+                    }
+                }
+            }
+        }
+        if now - self.last_sweep > scenario.sweep_ms * 1_000_000 {
+            self.fdbs.values_mut().for_each(|fdb| {
+                fdb.sweep(now_us);
+            });
+            self.last_sweep = now;
+        }
+    }
+}
+
+/// Statistics of delivery of every frame sent.
+///
+/// Times are nanoseconds since the beginning of simulation.
+struct DeliveryStat {
+    sent: Vec<u64>,
+    delivered: Vec<Option<u64>>,
+    /// Count of times each frame is put on a segment.
+    transmissions: Vec<u32>,
+    /// Count of times each frame reaches the segment of its destination.
+    deliveries: Vec<u32>,
+}
+
+impl DeliveryStat {
+    fn new(count: usize) -> Self {
+        DeliveryStat {
+            sent: Vec::with_capacity(count),
+            delivered: Vec::with_capacity(count),
+            transmissions: Vec::with_capacity(count),
+            deliveries: Vec::with_capacity(count),
+        }
+    }
+
+    /// Record a frame being sent and return its index.
+    fn send(&mut self, now: u64) -> usize {
+        self.sent.push(now);
+        self.delivered.push(None);
+        self.transmissions.push(0);
+        self.deliveries.push(0);
+        self.sent.len() - 1
+    }
+
+    fn len(&self) -> usize {
+        self.sent.len()
+    }
+
+    /// Export scatter of deliveries, with latency of the first delivery (-1 if never delivered),
+    /// transmissions and deliveries of each frame.
+    fn export_delivery_scatter(&self, scenario: &Scenario) {
+        let sc_delivery = (0..self.len())
+            .map(|i| {
+                let latency = self.delivered[i].map(|t| ((t - self.sent[i]) / 1000) as i64).unwrap_or(-1);
+                vec![(self.sent[i] / 1000) as i64, latency, self.transmissions[i] as i64,
+                     self.deliveries[i] as i64]
+            })
+            .collect::<Vec<_>>();
+        serde_pickle::to_writer(&mut BufWriter::new(File::create(scenario.out_path("sc_net_delivery.pkl")).unwrap()),
+                                &sc_delivery, SerOptions::default()).unwrap();
+    }
+}
+
+/// Convergence of spanning tree from the beginning (without a segment) or from a segment going
+/// down or coming up, as the time of the change and the time to converge.
+type Convergence = (Option<(usize, bool)>, u64, u64);
+
+/// Statistics of spanning tree, with changes of ports and of segments.
+///
+/// Times are nanoseconds since the beginning of simulation.
+struct StpStat {
+    records: Vec<(usize, usize, PortRole, PortState)>,
+    times: Vec<u64>,
+    /// Segments going down or coming back up, with the time.
+    links: Vec<(usize, bool, u64)>,
+}
+
+impl StpStat {
+    fn new() -> Self {
+        StpStat { records: Vec::new(), times: Vec::new(), links: Vec::new() }
+    }
+
+    /// Record a change of role or state of a port of a bridge.
+    fn rec(&mut self, bridge: usize, port: usize, role: PortRole, state: PortState, now: u64) {
+        self.records.push((bridge, port, role, state));
+        self.times.push(now);
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Time from the beginning and from each change of segments to the last change of ports before
+    /// the next one, i.e. the time to converge.
+    fn convergence(&self) -> Vec<Convergence> {
+        let mut begins = vec![(None, 0)];
+        begins.extend(self.links.iter().map(|(lan, up, t)| (Some((*lan, *up)), *t)));
+        (0..begins.len())
+            .map(|i| {
+                let (link, begin) = begins[i];
+                let end = begins.get(i + 1).map(|(_, t)| *t).unwrap_or(u64::MAX);
+                let last = self.times.iter().copied().filter(|t| *t >= begin && *t < end).max();
+                (link, begin, last.map(|t| t - begin).unwrap_or(0))
+            })
+            .collect()
+    }
+
+    /// Export scatter of changes of ports, with bridge, port, role and state.
+    fn export_port_scatter(&self, scenario: &Scenario) {
+        let sc_stp_ports = self.records.iter()
+            .zip(self.times.iter())
+            .map(|((bridge, port, role, state), t)| {
+                vec![(t / 1000) as i64, *bridge as i64, *port as i64, *role as i64, *state as i64]
+            })
+            .collect::<Vec<_>>();
+        serde_pickle::to_writer(&mut BufWriter::new(File::create(scenario.out_path("sc_stp_ports.pkl")).unwrap()),
+                                &sc_stp_ports, SerOptions::default()).unwrap();
+    }
+
+    /// Export scatter of convergence after changes of segments, with the segment, whether it came
+    /// up, and the time to converge.
+    fn export_convergence_scatter(&self, scenario: &Scenario) {
+        let sc_stp_convergence = self.convergence().into_iter()
+            .filter_map(|(link, t, conv)| link.map(|(lan, up)| {
+                vec![(t / 1000) as i64, lan as i64, up as i64, (conv / 1000) as i64]
+            }))
+            .collect::<Vec<_>>();
+        serde_pickle::to_writer(&mut BufWriter::new(File::create(scenario.out_path("sc_stp_convergence.pkl")).unwrap()),
+                                &sc_stp_convergence, SerOptions::default()).unwrap();
+    }
+}
+
+/// Simulation of a network of bridges on a virtual clock.
+struct NetworkSim<'a> {
+    scenario: &'a Scenario,
+    network: Network,
+    bridges: Vec<NetBridge>,
+    /// Segment of the network where each generated segment is.
+    seg_lan: BTreeMap<Segment, usize>,
+    /// Segment of the network where each station is.
+    location: HashMap<Address, usize>,
+    /// Whether each segment is up.
+    lan_up: Vec<bool>,
+    /// Frames put on segments that are down.
+    lost: usize,
+    bpdu_cnt: usize,
+    delivery: DeliveryStat,
+    stp_stat: StpStat,
+    engine: Engine<Job>,
+}
+
+impl<'a> NetworkSim<'a> {
+    fn new(scenario: &'a Scenario, network: Network, migrations: &[Migration],
+           mapping: BTreeMap<Address, Segment>, frame_cnt: usize) -> Self {
+        // spread generated segments over segments of the network hosting stations
+        let hosts = (0..network.lans.len()).filter(|i| network.lans[*i].stations).collect::<Vec<_>>();
+        assert!(!hosts.is_empty(), "no segment of the topology hosts stations");
+        let segments = mapping.values().chain(migrations.iter().map(|m| &m.seg)).collect::<BTreeSet<_>>();
+        let seg_lan = segments.into_iter()
+            .enumerate()
+            .map(|(i, seg)| (*seg, hosts[i % hosts.len()]))
+            .collect::<BTreeMap<_, _>>();
+        let location = mapping.iter().map(|(addr, seg)| (*addr, seg_lan[seg])).collect();
+        let timers = Timers {
+            hello: scenario.stp_hello_ms,
+            max_age: scenario.stp_max_age_ms,
+            forward_delay: scenario.stp_forward_delay_ms,
+        };
+        let bridges = network.ports.iter()
+            .enumerate()
+            .map(|(i, ports)| {
+                let costs = ports.iter().map(|lan| network.lans[*lan].cost).collect::<Vec<_>>();
+                let edges = ports.iter().map(|lan| network.lans[*lan].edge).collect::<Vec<_>>();
+                let id = bridge_id(network.priorities[i], i);
+                let stp = match network.spanning_trees[i] {
+                    SpanningTree::None => None,
+                    SpanningTree::Stp => Some(Stp::new(id, &costs, timers)),
+                    SpanningTree::Rstp => Some(Stp::new(id, &costs, timers).with_rapid(&edges)),
+                };
+                NetBridge::new(scenario, network.vlans[i].clone(), stp, scenario.seed.wrapping_add(i as u64))
+            })
+            .collect();
+        NetworkSim {
+            scenario,
+            lan_up: vec![true; network.lans.len()],
+            network,
+            bridges,
+            seg_lan,
+            location,
+            lost: 0,
+            bpdu_cnt: 0,
+            delivery: DeliveryStat::new(frame_cnt),
+            stp_stat: StpStat::new(),
+            engine: Engine::new(),
+        }
+    }
+
+    /// Run the spanning tree of a bridge if it has one, then act on its output.
+    fn stp(&mut self, bridge: usize, now: u64, f: impl FnOnce(&mut Stp, u64, &mut Vec<StpAction>)) {
+        let now_ms = now / 1_000_000;
+        let mut actions = Vec::new();
+        let b = &mut self.bridges[bridge];
+        let Some(stp) = &mut b.stp else {
+            return;
+        };
+        f(stp, now_ms, &mut actions);
+        // age out faster while the topology is changing
+        let aging = if stp.topology_change(now_ms) {
+            b.aging.min(stp.timers().forward_delay * 1000)
+        } else {
+            b.aging
+        };
+        b.set_aging(aging);
+        for action in actions {
+            match action {
+                StpAction::Send(port, bpdu) => self.send_bpdu(now, bridge, port, bpdu),
+                StpAction::Changed(port, role, state) => {
+                    self.stp_stat.rec(bridge, port, role, state, now);
+                    if !matches!(state, PortState::Learning | PortState::Forwarding) {
+                        self.bridges[bridge].forget(port);
+                    }
+                }
+                StpAction::Flush(port) => {
+                    self.bridges[bridge].forget(port);
+                }
+            }
+        }
+    }
+
+    /// Send a BPDU to the other bridges on the segment of a port.
+    fn send_bpdu(&mut self, now: u64, bridge: usize, port: usize, bpdu: Bpdu) {
+        let lan = self.network.ports[bridge][port];
+        if !self.lan_up[lan] {
+            return;
+        }
+        self.bpdu_cnt += 1;
+        let at = now + self.network.lans[lan].delay_ns;
+        for &(b, p) in &self.network.lans[lan].ports {
+            if (b, p) != (bridge, port) {
+                self.engine.schedule(at, Job::Bpdu(b, p, bpdu));
+            }
+        }
+    }
+
+    /// Take a segment down or bring it back up.
+    fn link(&mut self, now: u64, lan: usize, up: bool) {
+        info!(target: "network", "Segment {} {} at {} ms.", self.network.lans[lan].name,
+            if up { "up" } else { "down" }, now / 1_000_000);
+        self.lan_up[lan] = up;
+        self.stp_stat.links.push((lan, up, now));
+        for (bridge, port) in self.network.lans[lan].ports.clone() {
+            if self.bridges[bridge].stp.is_none() && !up {
+                self.bridges[bridge].forget(port);
+            }
+            if up {
+                self.stp(bridge, now, |stp, t, actions| stp.link_up(port, t, &mut |a| actions.push(a)));
+            } else {
+                self.stp(bridge, now, |stp, t, actions| stp.link_down(port, t, &mut |a| actions.push(a)));
+            }
+        }
+    }
+
+    /// Put a copy of a frame on a segment, from a port of a bridge or from its source station.
+    fn transmit(&mut self, now: u64, lan: usize, from: Option<(usize, usize)>, transit: &Transit) {
+        if !self.lan_up[lan] {
+            self.lost += 1;
+            return;
+        }
+        let at = now + self.network.lans[lan].delay_ns;
+        self.delivery.transmissions[transit.id] += 1;
+        if self.location.get(&transit.frame.dst) == Some(&lan) {
+            self.delivery.deliveries[transit.id] += 1;
+            self.delivery.delivered[transit.id].get_or_insert(at);
+        }
+        for &(bridge, port) in &self.network.lans[lan].ports {
+            if from != Some((bridge, port)) {
+                let copy = Transit { id: transit.id, frame: transit.frame.clone(), hops: transit.hops };
+                self.engine.schedule(at, Job::Arrive(bridge, port, copy));
+            }
+        }
+    }
+
+    /// Handle an event from the orchestrator.
+    fn handle(&mut self, event: Event, now: u64) {
+        match event {
+            Event::Request(frame) => {
+                let id = self.delivery.send(now);
+                let lan = self.seg_lan[&frame.src_seg];
+                self.transmit(now, lan, None, &Transit { id, frame, hops: 0 });
+            }
+            Event::Migrate(addr, seg) => {
+                self.location.insert(addr, self.seg_lan[&seg]);
+            }
+            Event::Report(_) => {
+                // bridges of the network do not snoop, flooding multicast frames everywhere
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Handle a copy of a frame arriving at a port of a bridge.
+    fn arrive(&mut self, now: u64, bridge: usize, port: usize, transit: Transit) {
+        let b = &mut self.bridges[bridge];
+        if transit.hops >= self.scenario.max_hops {
+            // caught in a loop, most likely
+            b.meter.dropped += 1;
+            return;
+        }
+        b.free = b.free.max(now) + self.scenario.bridge_service_ns;
+        let free = b.free;
+        let mut out = Vec::new();
+        b.receive(self.scenario, &transit.frame, port, free, &mut |p, tag| out.push((p, tag)));
+        for (p, vlan) in out {
+            let frame = Frame { vlan, ..transit.frame.clone() };
+            let copy = Transit { id: transit.id, frame, hops: transit.hops + 1 };
+            self.transmit(free, self.network.ports[bridge][p], Some((bridge, p)), &copy);
+        }
+    }
+
+    fn report(&self) {
+        let sum = |f: fn(&NetBridgeMeter) -> usize| self.bridges.iter().map(|b| f(&b.meter)).sum::<usize>();
+        info!(target: "network", "Bridges received {} frames in total, forwarded {}, flooded {} unknown \
+            unicasts, {} broadcasts and {} multicasts, filtered {}, dropped {}, blocked {} and {} of foreign \
+            VLANs. Lost {} frames on segments down, sent {} BPDUs.", sum(|m| m.received), sum(|m| m.forwarded),
+            sum(|m| m.flooded), sum(|m| m.broadcast), sum(|m| m.multicast), sum(|m| m.filtered),
+            sum(|m| m.dropped), sum(|m| m.blocked), sum(|m| m.foreign), self.lost, self.bpdu_cnt);
+    }
+
+    /// Export statistics.
+    fn export(&self) {
+        let delivered = self.delivery.delivered.iter().filter(|t| t.is_some()).count();
+        let transmissions = self.delivery.transmissions.iter().map(|n| *n as usize).sum::<usize>();
+        info!(target: "network", "Delivered {} of {} frames, with {:.2} transmissions per frame.",
+            delivered, self.delivery.len(), transmissions as f64 / self.delivery.len().max(1) as f64);
+        let mut sc_bridges = Vec::with_capacity(self.bridges.len());
+        for (name, b) in self.network.bridges.iter().zip(&self.bridges) {
+            let (m, fdb) = (&b.meter, b.stats());
+            info!(target: "network", "Bridge {}: received {}, forwarded {}, flooded {} (broadcast {}, \
+                multicast {}), filtered {}, dropped {}, blocked {}, foreign {}. Tables of {} VLANs, hit rate \
+                {:.4}, {} evicted and {} aged out.", name, m.received, m.forwarded, m.flooded, m.broadcast,
+                m.multicast, m.filtered, m.dropped, m.blocked, m.foreign, b.fdbs.len(), fdb.hit_rate(),
+                fdb.evicted, fdb.aged);
+            if let Some(stp) = &b.stp {
+                let roles = (0..b.port_cnt()).map(|p| format!("{:?}", stp.role(p))).collect::<Vec<_>>();
+                info!(target: "network", "Bridge {} ({}): root {} at cost {}, ports {}.", name, stp.id(),
+                    stp.root(), stp.root_cost(), roles.join(", "));
+            }
+            sc_bridges.push((name.clone(), vec![m.received as i64, m.forwarded as i64, m.flooded as i64,
+                m.filtered as i64, m.dropped as i64, m.blocked as i64, m.foreign as i64, fdb.hits as i64,
+                fdb.misses as i64, fdb.evicted as i64, fdb.aged as i64, m.broadcast as i64, m.multicast as i64]));
+        }
+        if self.bridges.iter().any(|b| b.stp.is_some()) {
+            info!(target: "network", "Recorded {} changes of ports in spanning tree.", self.stp_stat.len());
+            for (link, t, conv) in self.stp_stat.convergence() {
+                match link {
+                    None => info!(target: "network", "Spanning tree converged initially in {:.3} ms.",
+                        conv as f64 / 1e6),
+                    Some((lan, up)) => info!(target: "network", "Spanning tree converged in {:.3} ms after \
+                        segment {} {} at {} ms.", conv as f64 / 1e6, self.network.lans[lan].name,
+                        if up { "up" } else { "down" }, t / 1_000_000),
+                }
+            }
+            self.stp_stat.export_port_scatter(self.scenario);
+            self.stp_stat.export_convergence_scatter(self.scenario);
+        }
+        serde_pickle::to_writer(&mut BufWriter::new(File::create(self.scenario.out_path("sc_net_bridges.pkl")).unwrap()),
+                                &sc_bridges, SerOptions::default()).unwrap();
+        self.delivery.export_delivery_scatter(self.scenario);
+    }
+
+    fn summary(&self) -> Summary {
+        Summary {
+            delivered: self.delivery.delivered.iter().filter(|t| t.is_some()).count(),
+            blocked: self.bridges.iter().map(|b| b.meter.blocked).sum(),
+            lost: self.lost,
+            convergence: self.stp_stat.convergence(),
+        }
+    }
+}
+
+/// Outcome of a simulation of a network, for comparison with others.
+pub struct Summary {
+    delivered: usize,
+    blocked: usize,
+    lost: usize,
+    convergence: Vec<Convergence>,
+}
+
+/// Identifier of a bridge in spanning tree, with an address made of its index.
+fn bridge_id(priority: u16, index: usize) -> BridgeId {
+    let [hi, lo] = (index as u16).to_be_bytes();
+    BridgeId { priority, addr: Address { data: [0x02, 0x00, 0x00, 0x00, hi, lo] } }
+}
+
+/// Run the simulation of a network of bridges described by a topology on a virtual clock.
+///
+/// Frames enter the network on the segment of their source and travel until no bridge forwards
+/// them any more. If bridges run spanning tree, frames start being sent after it has had time to
+/// converge, and times of failures count from then.
+pub fn run(scenario: &Scenario, topology: &Topology, frames: Frames, migrations: Vec<Migration>,
+           memberships: Vec<Membership>, mapping: BTreeMap<Address, Segment>) -> Summary {
+    info!(target: "network", "Network simulation started.");
+    let network = topology.resolve(scenario.link_delay_ns, scenario.spanning_tree);
+    info!(target: "network", "{} bridges on {} segments.", network.bridges.len(), network.lans.len());
+    let stp = network.spanning_trees.iter().any(|s| *s != SpanningTree::None);
+    // time for spanning tree to converge, and to converge again after a change of segments
+    let settle_ms = scenario.stp_max_age_ms + 2 * scenario.stp_forward_delay_ms + scenario.stp_hello_ms;
+    let warmup_ms = if stp { settle_ms } else { 0 };
+    let end_ms = network.failures.iter()
+        .map(|f| f.restore_ms.unwrap_or(f.at_ms).max(f.at_ms) + settle_ms)
+        .fold(scenario.elapse_sec as u64 * 1000, u64::max) + warmup_ms;
+    let failures = network.failures.clone();
+    let mut sim = NetworkSim::new(scenario, network, &migrations, mapping, frames.len());
+    let buckets = distribute(frames.len(), scenario.elapse_sec, &scenario.distribution, scenario.seed);
+    let mut sequencer = Sequencer::new(frames, migrations, memberships);
+    for failure in failures {
+        sim.engine.schedule((warmup_ms + failure.at_ms) * 1_000_000, Job::Link(failure.lan, false));
+        if let Some(restore_ms) = failure.restore_ms {
+            sim.engine.schedule((warmup_ms + restore_ms) * 1_000_000, Job::Link(failure.lan, true));
+        }
+    }
+    for bridge in 0..sim.bridges.len() {
+        sim.stp(bridge, 0, |stp, t, actions| stp.start(t, &mut |a| actions.push(a)));
+    }
+    if stp {
+        sim.engine.schedule(0, Job::StpTick);
+    }
+    sim.engine.schedule(warmup_ms * 1_000_000, Job::Tick(0));
+    while let Some((now, job)) = sim.engine.next() {
+        match job {
+            Job::Tick(i) => {
+                // spread the frames of the bucket evenly within the millisecond
+                let mut events = Vec::new();
+                sequencer.send(buckets[i], &mut |event| events.push(event));
+                let step = 1_000_000 / events.len().max(1) as u64;
+                for (k, event) in events.into_iter().enumerate() {
+                    sim.engine.schedule(now + k as u64 * step, Job::Send(event));
+                }
+                if i + 1 < buckets.len() {
+                    sim.engine.schedule(now + 1_000_000, Job::Tick(i + 1));
+                }
+                if (i as u64).is_multiple_of(scenario.orchestrator_log_ms) {
+                    info!(target: "network", "Simulated {} ms, {} jobs queued.", i, sim.engine.len());
+                    sim.report();
+                }
+            }
+            Job::Send(event) => sim.handle(event, now),
+            Job::Arrive(bridge, port, transit) => sim.arrive(now, bridge, port, transit),
+            Job::Bpdu(bridge, port, bpdu) => {
+                sim.stp(bridge, now, |stp, t, actions| stp.receive(port, bpdu, t, &mut |a| actions.push(a)));
+            }
+            Job::StpTick => {
+                for bridge in 0..sim.bridges.len() {
+                    sim.stp(bridge, now, |stp, t, actions| stp.tick(t, &mut |a| actions.push(a)));
+                }
+                if now < end_ms * 1_000_000 {
+                    sim.engine.schedule(now + scenario.stp_tick_ms * 1_000_000, Job::StpTick);
+                }
+            }
+            Job::Link(lan, up) => sim.link(now, lan, up),
+        }
+    }
+    sim.report();
+    sim.export();
+    info!(target: "network", "Network simulation finished.");
+    sim.summary()
+}
+
+/// Run the simulation of a network with classic then rapid spanning tree on all bridges, and
+/// compare their convergence after the same changes of segments.
+///
+/// Frames are loaded again for each run, so that they need not be held in memory.
+/// Outputs of each run are prefixed by `stp_` and `rstp_`, and the comparison is exported as a
+/// scatter of the time of each change, the segment (-1 for the beginning), whether it came up and
+/// the time to converge with each protocol.
+pub fn compare(scenario: &Scenario, topology: &Topology, migrations: Vec<Migration>,
+               memberships: Vec<Membership>, mapping: BTreeMap<Address, Segment>) {
+    let mut topology = topology.clone();
+    topology.bridges.iter_mut().for_each(|b| b.spanning_tree = None);
+    let mut summaries = Vec::new();
+    for (name, spanning_tree) in [("stp", SpanningTree::Stp), ("rstp", SpanningTree::Rstp)] {
+        let mut scenario = scenario.clone();
+        scenario.out_prefix = format!("{}{}_", scenario.out_prefix, name);
+        scenario.spanning_tree = spanning_tree;
+        summaries.push(run(&scenario, &topology, crate::load_frames(&scenario), migrations.clone(),
+                           memberships.clone(), mapping.clone()));
+    }
+    let [stp, rstp] = &summaries[..] else {
+        unreachable!()
+    };
+    info!(target: "network", "Classic spanning tree delivered {} frames, blocked {} and lost {}; rapid \
+        delivered {}, blocked {} and lost {}.", stp.delivered, stp.blocked, stp.lost, rstp.delivered,
+        rstp.blocked, rstp.lost);
+    let names = topology.resolve(scenario.link_delay_ns, SpanningTree::Stp).lans
+        .into_iter()
+        .map(|lan| lan.name)
+        .collect::<Vec<_>>();
+    let mut sc_stp_compare = Vec::new();
+    for ((link, t, classic), (_, _, rapid)) in stp.convergence.iter().zip(&rstp.convergence) {
+        let change = match link {
+            None => "initially".to_string(),
+            Some((lan, up)) => format!("after segment {} {} at {} ms", names[*lan],
+                                       if *up { "up" } else { "down" }, t / 1_000_000),
+        };
+        info!(target: "network", "Converged {}: classic in {:.3} ms, rapid in {:.3} ms.", change,
+            *classic as f64 / 1e6, *rapid as f64 / 1e6);
+        let (lan, up) = link.map(|(lan, up)| (lan as i64, up as i64)).unwrap_or((-1, 1));
+        sc_stp_compare.push(vec![(t / 1000) as i64, lan, up, (classic / 1000) as i64, (rapid / 1000) as i64]);
+    }
+    serde_pickle::to_writer(&mut BufWriter::new(File::create(scenario.out_path("sc_stp_compare.pkl")).unwrap()),
+                            &sc_stp_compare, SerOptions::default()).unwrap();
+}
@@ -0,0 +1,90 @@
+use std::iter::Peekable;
+use std::thread;
+use std::time::{Duration, Instant};
+use std::vec::IntoIter;
+use log::info;
+use net_exp_bridge::{Membership, Migration};
+use crate::arrival::Distribution;
+use crate::{Event, Frames};
+use crate::queue::Inlet;
+use crate::scenario::Scenario;
+
+/// Distribute a count of frames per milliseconds in specified duration with a model of arrival,
+/// returning the count of frames in each millisecond.
+pub fn distribute(frame_cnt: usize, dur_sec: usize, dist: &Distribution, seed: u64) -> Vec<usize> {
+    let counts = dist.counts(frame_cnt, dur_sec * 1000, &mut fastrand::Rng::with_seed(seed));
+    info!(target: "orchestrator", "Distributed {} frames in {} ms, at most {} in one.",
+        frame_cnt, counts.len(), counts.iter().max().unwrap_or(&0));
+    counts
+}
+
+/// Sequence of frames being sent, moving stations and changing groups as scheduled along the way.
+pub struct Sequencer {
+    frames: Frames,
+    index: usize,
+    migrations: Peekable<IntoIter<Migration>>,
+    memberships: Peekable<IntoIter<Membership>>,
+}
+
+impl Sequencer {
+    pub fn new(frames: Frames, migrations: Vec<Migration>, memberships: Vec<Membership>) -> Self {
+        Sequencer {
+            frames,
+            index: 0,
+            migrations: migrations.into_iter().peekable(),
+            memberships: memberships.into_iter().peekable(),
+        }
+    }
+
+    /// Send the next count of frames to the bridge, or as many as left, and return the count of them.
+    ///
+    /// Migrations and memberships scheduled before a frame are sent prior to it.
+    pub fn send(&mut self, count: usize, te: &mut impl FnMut(Event)) -> usize {
+        let mut sent = 0;
+        for frame in self.frames.by_ref().take(count) {
+            while let Some(migration) = self.migrations.next_if(|m| m.index <= self.index) {
+                te(Event::Migrate(migration.addr, migration.seg));
+            }
+            while let Some(membership) = self.memberships.next_if(|m| m.index <= self.index) {
+                te(Event::Report(membership));
+            }
+            te(Event::Request(frame));
+            self.index += 1;
+            sent += 1;
+        }
+        sent
+    }
+}
+
+/// Orchestration service that send frames to the bridge with distributed frame sequence.
+pub fn orchestrator(scenario: Scenario, frame_seq: Frames, migrations: Vec<Migration>,
+                    memberships: Vec<Membership>, te: Inlet<Event>) {
+    info!(target: "orchestrator", "Orchestrator started.");
+    let counts = distribute(frame_seq.len(), scenario.elapse_sec, &scenario.distribution, scenario.seed);
+    let mut sequencer = Sequencer::new(frame_seq, migrations, memberships);
+    let mut te = |event| te.send(event).unwrap();
+    let begin = Instant::now();
+    let mut last = 0;
+    let mut last_t = Instant::now();
+    let mut count = 0;
+    loop {
+        let now = Instant::now();
+        let dur = now.duration_since(begin);
+        let cur = dur.as_secs() * 1000 + dur.subsec_millis() as u64;
+        if cur >= counts.len() as u64 {
+            sequencer.send(counts[last..].iter().sum(), &mut te);
+            break;
+        }
+        if cur > last as u64 {
+            count += sequencer.send(counts[last..cur as usize].iter().sum(), &mut te);
+            last = cur as usize;
+        }
+        if now.duration_since(last_t) > Duration::from_millis(scenario.orchestrator_log_ms) {
+            info!(target: "orchestrator", "Sent {} frames.", count);
+            count = 0;
+            last_t = now;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    info!(target: "orchestrator", "Orchestrator exiting.");
+}
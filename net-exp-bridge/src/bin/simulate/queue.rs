@@ -0,0 +1,245 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SendError, Sender, SyncSender, TrySendError};
+use std::time::Duration;
+use std::str::FromStr;
+use serde::Deserialize;
+use serde_pickle::SerOptions;
+use net_exp_bridge::Frame;
+use crate::scenario::Scenario;
+
+/// Policy to drop frames arriving at a full or congested queue.
+///
+/// Messages not carrying frames (answers of broadcasts, migrations, reports, shutdown) are never
+/// dropped, though they take room in the queue and may fill it over capacity.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum QueuePolicy {
+    /// Drop frames arriving when the queue is full.
+    TailDrop,
+    /// Random early detection, dropping frames at random as the average depth grows.
+    Red(Red),
+    /// Drop frames of lower priority earlier, those of priority 0 (or untagged) being admitted while
+    /// the queue is less than half full and those of priority 7 until it is full.
+    Priority,
+}
+
+/// Parameters of `QueuePolicy::Red`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Red {
+    /// Average depth as fraction of capacity from which frames are dropped at random
+    pub min_th: f64,
+    /// Average depth as fraction of capacity from which all frames are dropped
+    pub max_th: f64,
+    /// Probability of drop when the average depth reaches `max_th`
+    pub max_p: f64,
+    /// Weight of the current depth in the moving average
+    pub weight: f64,
+}
+
+impl Default for Red {
+    fn default() -> Self {
+        Red { min_th: 0.25, max_th: 0.75, max_p: 0.1, weight: 0.002 }
+    }
+}
+
+impl FromStr for QueuePolicy {
+    type Err = String;
+
+    /// Parse the name of a policy with default parameters.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tail_drop" => Ok(QueuePolicy::TailDrop),
+            "red" => Ok(QueuePolicy::Red(Red::default())),
+            "priority" => Ok(QueuePolicy::Priority),
+            _ => Err(format!("unknown queue policy \"{}\", expected tail_drop, red or priority", s)),
+        }
+    }
+}
+
+/// Priority of a frame, from its VLAN tag or 0 if untagged.
+pub fn priority(frame: &Frame) -> u8 {
+    frame.vlan.map_or(0, |tag| tag.pcp)
+}
+
+/// Statistics of a queue, sampled at each arrival if the queue has a limit, nothing being
+/// dropped otherwise.
+///
+/// Times are nanoseconds since the beginning of simulation.
+pub struct QueueStat {
+    /// Depth found by the arrival and count of frames dropped so far.
+    pub records: Vec<(usize, usize)>,
+    pub times: Vec<u64>,
+}
+
+impl QueueStat {
+    fn new() -> Self {
+        QueueStat { records: Vec::new(), times: Vec::new() }
+    }
+
+    fn rec(&mut self, depth: usize, dropped: usize, now: u64) {
+        self.records.push((depth, dropped));
+        self.times.push(now);
+    }
+
+    /// Export scatter of the depth of the queue and cumulative drops.
+    fn export_queue_scatter(&self, scenario: &Scenario, name: &str) {
+        let sc_queue = self.records.iter()
+            .zip(self.times.iter())
+            .map(|((depth, dropped), t)| vec![(t / 1000) as i64, *depth as i64, *dropped as i64])
+            .collect::<Vec<_>>();
+        let path = scenario.out_path(&format!("sc_{}_queue.pkl", name));
+        serde_pickle::to_writer(&mut BufWriter::new(File::create(path).unwrap()), &sc_queue,
+                                SerOptions::default()).unwrap();
+    }
+}
+
+/// Queue in front of a server, deciding which arrivals to admit.
+///
+/// Only the depth is told, the admitted items being held by whoever serves them.
+pub struct Queue {
+    /// Maximum count of items waiting, or 0 for no limit.
+    capacity: usize,
+    policy: QueuePolicy,
+    /// Moving average of depth, for random early detection.
+    avg: f64,
+    rng: fastrand::Rng,
+    dropped: usize,
+    stat: QueueStat,
+}
+
+impl Queue {
+    pub fn new(capacity: usize, policy: QueuePolicy, seed: u64) -> Self {
+        Queue { capacity, policy, avg: 0.0, rng: fastrand::Rng::with_seed(seed), dropped: 0, stat: QueueStat::new() }
+    }
+
+    /// Decide whether an item arriving at specified time enters the queue holding a count of
+    /// items, with the priority of the frame it carries, or `None` if it carries no frame.
+    ///
+    /// Items without frames are always admitted, even over capacity, while the depth they make is
+    /// held against frames arriving after them.
+    pub fn admit(&mut self, depth: usize, priority: Option<u8>, now: u64) -> bool {
+        let admitted = match priority {
+            _ if self.capacity == 0 => true,
+            None => true,
+            Some(_) if depth >= self.capacity => false,
+            Some(priority) => match &self.policy {
+                QueuePolicy::TailDrop => true,
+                QueuePolicy::Red(red) => {
+                    self.avg = (1.0 - red.weight) * self.avg + red.weight * depth as f64;
+                    let min_th = red.min_th * self.capacity as f64;
+                    let max_th = red.max_th * self.capacity as f64;
+                    if self.avg < min_th {
+                        true
+                    } else if self.avg >= max_th {
+                        false
+                    } else {
+                        self.rng.f64() >= red.max_p * (self.avg - min_th) / (max_th - min_th)
+                    }
+                }
+                QueuePolicy::Priority => depth * 14 < self.capacity * (7 + priority.min(7) as usize),
+            },
+        };
+        if !admitted {
+            self.dropped += 1;
+        }
+        if self.capacity > 0 {
+            self.stat.rec(depth, self.dropped, now);
+        }
+        admitted
+    }
+
+    /// Count of frames dropped so far.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Maximum count of items waiting, or 0 for no limit.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Export statistics of the queue, in `sc_<name>_queue.pkl`, empty if the queue has no limit.
+    pub fn export(&self, scenario: &Scenario, name: &str) {
+        self.stat.export_queue_scatter(scenario, name);
+    }
+}
+
+/// Sending end of the channel feeding a queue, holding as many items as the queue can, or any
+/// count if it has no limit.
+///
+/// Senders wait while the channel is full, so that a server falling behind slows down those
+/// feeding it instead of piling up their items.
+pub enum Inlet<T> {
+    Unbounded(Sender<T>),
+    Bounded(SyncSender<T>),
+}
+
+impl<T> Clone for Inlet<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Inlet::Unbounded(tx) => Inlet::Unbounded(tx.clone()),
+            Inlet::Bounded(tx) => Inlet::Bounded(tx.clone()),
+        }
+    }
+}
+
+impl<T> Inlet<T> {
+    /// Channel holding a count of items, or any count if 0.
+    pub fn channel(capacity: usize) -> (Inlet<T>, Receiver<T>) {
+        if capacity == 0 {
+            let (tx, rx) = std::sync::mpsc::channel();
+            (Inlet::Unbounded(tx), rx)
+        } else {
+            let (tx, rx) = std::sync::mpsc::sync_channel(capacity);
+            (Inlet::Bounded(tx), rx)
+        }
+    }
+
+    /// Send an item, waiting for room if the channel is full.
+    pub fn send(&self, item: T) -> Result<(), SendError<T>> {
+        match self {
+            Inlet::Unbounded(tx) => tx.send(item),
+            Inlet::Bounded(tx) => tx.send(item),
+        }
+    }
+
+    /// Send an item if there is room for it.
+    pub fn try_send(&self, item: T) -> Result<(), TrySendError<T>> {
+        match self {
+            Inlet::Unbounded(tx) => tx.send(item).map_err(|SendError(item)| TrySendError::Disconnected(item)),
+            Inlet::Bounded(tx) => tx.try_send(item),
+        }
+    }
+}
+
+/// Take the earliest item of a queue fed by a channel, waiting for one at most for some time if the
+/// queue is empty.
+///
+/// Items arrived in the channel while the server was busy are offered to the queue with its depth
+/// first, and enter it if returned. Fails once the channel is closed and the queue is empty, or if
+/// nothing arrives in time.
+pub fn receive<T>(rx: &Receiver<T>, queue: &mut VecDeque<T>, wait: Option<Duration>,
+                  mut offer: impl FnMut(T, usize) -> Option<T>) -> Result<T, RecvTimeoutError> {
+    loop {
+        if queue.is_empty() {
+            let item = match wait {
+                Some(wait) => rx.recv_timeout(wait)?,
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected)?,
+            };
+            if let Some(item) = offer(item, 0) {
+                queue.push_back(item);
+            }
+        }
+        while let Ok(item) = rx.try_recv() {
+            if let Some(item) = offer(item, queue.len()) {
+                queue.push_back(item);
+            }
+        }
+        if let Some(item) = queue.pop_front() {
+            return Ok(item);
+        }
+    }
+}
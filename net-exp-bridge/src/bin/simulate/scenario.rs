@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use net_exp_bridge::fdb::EvictionPolicy;
+use net_exp_bridge::Segment;
+use crate::arrival::Distribution;
+use crate::bridge::HoldOverflow;
+use crate::facility::ReplyDelay;
+use crate::link::Link;
+use crate::queue::QueuePolicy;
+use crate::topology::SpanningTree;
+
+/// Scenario of simulation, read from a TOML or JSON file.
+///
+/// Missing keys take their default values.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    /// Run on a virtual clock with discrete events instead of threads on wall clock
+    pub discrete: bool,
+    /// Seed of randomness in simulation
+    pub seed: u64,
+    /// Directory to read generated files from
+    pub in_dir: PathBuf,
+    /// Prefix of names of generated files
+    pub in_prefix: String,
+    /// Text trace of frames to send instead of the generated ones
+    pub frame_trace: Option<PathBuf>,
+    /// Directory to write statistics in
+    pub out_dir: PathBuf,
+    /// Prefix of names of statistics files
+    pub out_prefix: String,
+    /// Duration in which frames are sent, in seconds
+    pub elapse_sec: usize,
+    /// Distribution of arrival of frames over the duration
+    pub distribution: Distribution,
+    /// Time after which a learned address is forgotten if its host stays silent
+    pub aging_ms: u64,
+    /// Interval between two sweeps of expired entries in the forwarding table
+    pub sweep_ms: u64,
+    /// Maximum count of entries in the forwarding table
+    pub table_capacity: usize,
+    /// Strategy to make room in the forwarding table when it is full
+    pub eviction_policy: EvictionPolicy,
+    /// Whether to flood frames to a multicast group only to segments its members reported from
+    pub igmp_snooping: bool,
+    /// Maximum count of events waiting for the bridge, or 0 for no limit, also bounding the channel
+    /// to the bridge in threaded mode
+    pub ingress_capacity: usize,
+    /// Maximum count of commands waiting for the facility, or 0 for no limit, also bounding the
+    /// channel to the facility in threaded mode
+    pub egress_capacity: usize,
+    /// Policy to drop frames arriving at a full or congested queue
+    pub queue_policy: QueuePolicy,
+    /// Interval between reports of the bridge
+    pub bridge_log_ms: u64,
+    /// Interval between reports of the facility
+    pub facility_log_ms: u64,
+    /// Interval between reports of the orchestrator
+    pub orchestrator_log_ms: u64,
+    /// Time for the bridge to handle an event in discrete mode
+    pub bridge_service_ns: u64,
+    /// Time for the facility to handle a command in discrete mode
+    pub facility_service_ns: u64,
+    /// Mean time for the segments to answer a broadcast
+    pub broadcast_delay_ns: u64,
+    /// Distribution of time for the segments to answer a broadcast
+    pub reply_delay: ReplyDelay,
+    /// Probability that the answer to a broadcast is lost
+    pub reply_loss: f64,
+    /// Time the bridge waits for an answer to a broadcast before broadcasting again, 0 to wait for ever
+    pub broadcast_timeout_ns: u64,
+    /// Count of broadcasts sent again for an address before the bridge gives up and discards its frames
+    pub broadcast_retries: u32,
+    /// Time a frame is held for the segment of its destination before it is given up, 0 for no limit
+    pub hold_timeout_ns: u64,
+    /// Maximum count of frames held for a destination, 0 for no limit
+    pub hold_per_addr: usize,
+    /// Maximum count of frames held for all destinations, 0 for no limit
+    pub hold_capacity: usize,
+    /// Frame given up when frames held reach a limit
+    pub hold_overflow: HoldOverflow,
+    /// Bit rate of segments attached to the bridge, unless told per segment
+    pub link_bitrate_bps: u64,
+    /// Maximum count of frames waiting to be sent on a segment, or 0 for no limit, unless told per segment
+    pub link_queue_capacity: usize,
+    /// Segments attached to the bridge with a bit rate, delay or output queue of their own
+    #[serde(rename = "segment")]
+    pub segments: Vec<SegmentLink>,
+    /// Topology file of a network of bridges to simulate instead of one bridge
+    pub topology: Option<PathBuf>,
+    /// Time for a frame to cross a segment, unless told per segment or by the topology
+    pub link_delay_ns: u64,
+    /// Count of bridges a frame may pass in the network before being dropped
+    pub max_hops: u32,
+    /// Spanning tree protocol of bridges in the network, unless the topology tells
+    pub spanning_tree: SpanningTree,
+    /// Interval between two configuration BPDUs from the root (from every bridge in rapid mode)
+    pub stp_hello_ms: u64,
+    /// Time after which spanning tree information not refreshed is discarded
+    pub stp_max_age_ms: u64,
+    /// Time a port spends listening and then learning before forwarding
+    pub stp_forward_delay_ms: u64,
+    /// Interval between two checks of timers of spanning tree
+    pub stp_tick_ms: u64,
+    /// Whether to simulate the network with classic then rapid spanning tree and compare them
+    pub compare_spanning_tree: bool,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario {
+            discrete: false,
+            seed: 0,
+            in_dir: PathBuf::from("."),
+            in_prefix: String::new(),
+            frame_trace: None,
+            out_dir: PathBuf::from("."),
+            out_prefix: String::new(),
+            elapse_sec: 10,
+            distribution: Distribution::HalfCircle,
+            aging_ms: 300_000,
+            sweep_ms: 50,
+            table_capacity: 8192,
+            eviction_policy: EvictionPolicy::Lru,
+            igmp_snooping: true,
+            ingress_capacity: 0,
+            egress_capacity: 0,
+            queue_policy: QueuePolicy::TailDrop,
+            bridge_log_ms: 50,
+            facility_log_ms: 250,
+            orchestrator_log_ms: 250,
+            bridge_service_ns: 500,
+            facility_service_ns: 200,
+            broadcast_delay_ns: 20_000,
+            reply_delay: ReplyDelay::Constant,
+            reply_loss: 0.0,
+            broadcast_timeout_ns: 0,
+            broadcast_retries: 2,
+            hold_timeout_ns: 0,
+            hold_per_addr: 0,
+            hold_capacity: 0,
+            hold_overflow: HoldOverflow::DropNewest,
+            link_bitrate_bps: 100_000_000,
+            link_queue_capacity: 0,
+            segments: Vec::new(),
+            topology: None,
+            link_delay_ns: 1000,
+            max_hops: 16,
+            spanning_tree: SpanningTree::None,
+            stp_hello_ms: 2000,
+            stp_max_age_ms: 20_000,
+            stp_forward_delay_ms: 15_000,
+            stp_tick_ms: 100,
+            compare_spanning_tree: false,
+        }
+    }
+}
+
+/// Segment attached to the bridge with link parameters of its own, others taking those of scenario.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SegmentLink {
+    /// Segment as `aa:bb`
+    pub seg: String,
+    pub bitrate_bps: Option<u64>,
+    pub delay_ns: Option<u64>,
+    pub queue_capacity: Option<usize>,
+}
+
+impl Scenario {
+    /// Load a scenario from file, as JSON if its extension is `.json` or TOML otherwise.
+    pub fn load(path: &Path) -> Self {
+        let text = fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("cannot read scenario {}: {}", path.display(), err));
+        if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&text)
+                .unwrap_or_else(|err| panic!("invalid scenario {}: {}", path.display(), err))
+        } else {
+            toml::from_str(&text)
+                .unwrap_or_else(|err| panic!("invalid scenario {}: {}", path.display(), err))
+        }
+    }
+
+    /// Check that the scenario can be simulated, failing with the reason otherwise.
+    pub fn validate(&self) {
+        assert!(self.elapse_sec > 0, "invalid scenario: elapse_sec must be at least 1");
+        assert!(self.reply_loss == 0.0 || self.broadcast_timeout_ns > 0 || self.hold_timeout_ns > 0,
+                "invalid scenario: reply_loss needs broadcast_timeout_ns or hold_timeout_ns, \
+                 or frames whose answer is lost are held for ever");
+        self.distribution.validate();
+    }
+
+    /// Link of a segment without parameters of its own.
+    pub fn default_link(&self) -> Link {
+        Link::new(self.link_bitrate_bps, self.link_delay_ns, self.link_queue_capacity)
+    }
+
+    /// Links of segments with parameters of their own.
+    pub fn links(&self) -> BTreeMap<Segment, Link> {
+        self.segments.iter()
+            .map(|desc| {
+                let segment = desc.seg.parse::<Segment>()
+                    .unwrap_or_else(|err| panic!("invalid segment {} in scenario: {}", desc.seg, err));
+                let link = Link::new(desc.bitrate_bps.unwrap_or(self.link_bitrate_bps),
+                                     desc.delay_ns.unwrap_or(self.link_delay_ns),
+                                     desc.queue_capacity.unwrap_or(self.link_queue_capacity));
+                (segment, link)
+            })
+            .collect()
+    }
+
+    /// Path of a generated file of specified name.
+    pub fn in_path(&self, name: &str) -> PathBuf {
+        self.in_dir.join(format!("{}{}", self.in_prefix, name))
+    }
+
+    /// Path of a statistics file of specified name.
+    pub fn out_path(&self, name: &str) -> PathBuf {
+        self.out_dir.join(format!("{}{}", self.out_prefix, name))
+    }
+}
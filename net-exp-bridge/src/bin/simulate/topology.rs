@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use serde::Deserialize;
+use net_exp_bridge::{VlanTag, DEFAULT_VLAN};
+
+/// Protocol of bridges to keep the active topology free of loops.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpanningTree {
+    /// Forward on all ports, loops bringing broadcast storms.
+    None,
+    /// Spanning Tree Protocol (IEEE 802.1D).
+    Stp,
+    /// Rapid Spanning Tree Protocol (IEEE 802.1w).
+    Rstp,
+}
+
+impl FromStr for SpanningTree {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(SpanningTree::None),
+            "stp" => Ok(SpanningTree::Stp),
+            "rstp" => Ok(SpanningTree::Rstp),
+            _ => Err(format!("unknown spanning tree protocol \"{}\", expected none, stp or rstp", s)),
+        }
+    }
+}
+
+/// Description of a network of bridges, read from a TOML or JSON file.
+///
+/// Bridges have ports attached to segments (shared LANs), and links connect two bridges directly
+/// by a point-to-point segment of their own. Failures take segments down during simulation.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Topology {
+    #[serde(default, rename = "segment")]
+    pub segments: Vec<SegmentDesc>,
+    #[serde(default, rename = "bridge")]
+    pub bridges: Vec<BridgeDesc>,
+    #[serde(default, rename = "link")]
+    pub links: Vec<LinkDesc>,
+    #[serde(default, rename = "failure")]
+    pub failures: Vec<FailureDesc>,
+}
+
+/// Segment where stations may be located.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SegmentDesc {
+    pub name: String,
+    /// Whether stations are located on the segment, or it only connects bridges
+    #[serde(default = "default_stations")]
+    pub stations: bool,
+    /// Time for a frame to cross the segment (the default of scenario if not specified)
+    pub delay_ns: Option<u64>,
+    /// Path cost of ports attached to the segment in spanning tree
+    pub cost: Option<u32>,
+    /// Whether ports attached are edge ones in rapid spanning tree (if stations are located on the
+    /// segment and only one bridge is attached if not specified)
+    pub edge: Option<bool>,
+}
+
+fn default_stations() -> bool {
+    true
+}
+
+/// Bridge with its ports, each attached to a segment.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BridgeDesc {
+    pub name: String,
+    /// Ports in order, as names of segments for access ports of the default VLAN
+    #[serde(default)]
+    pub ports: Vec<PortDesc>,
+    /// Priority in spanning tree, lower ones more likely to become the root
+    pub priority: Option<u16>,
+    /// Spanning tree protocol run by the bridge (the default of scenario if not specified)
+    pub spanning_tree: Option<SpanningTree>,
+}
+
+/// Port of a bridge, by name of its segment or with VLANs configured.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum PortDesc {
+    Segment(String),
+    Port(PortConfig),
+}
+
+impl PortDesc {
+    fn segment(&self) -> &str {
+        match self {
+            PortDesc::Segment(name) => name,
+            PortDesc::Port(config) => &config.segment,
+        }
+    }
+
+    fn vlan(&self) -> PortVlan {
+        match self {
+            PortDesc::Segment(_) => PortVlan::Access(DEFAULT_VLAN),
+            PortDesc::Port(config) => {
+                let vlan = config.vlan.unwrap_or(DEFAULT_VLAN);
+                match config.mode {
+                    PortMode::Access => PortVlan::Access(vlan),
+                    PortMode::Trunk => PortVlan::Trunk { native: vlan, allowed: config.allowed.clone() },
+                }
+            }
+        }
+    }
+}
+
+/// Port of a bridge with VLANs configured.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PortConfig {
+    pub segment: String,
+    #[serde(default)]
+    pub mode: PortMode,
+    /// VLAN of an access port, or native VLAN of a trunk port (the default VLAN if not specified)
+    pub vlan: Option<u16>,
+    /// VLANs carried by a trunk port (all if not specified)
+    pub allowed: Option<Vec<u16>>,
+}
+
+/// How a port treats VLANs, as on IEEE 802.1Q switches.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortMode {
+    /// Untagged frames of one VLAN, for stations.
+    #[default]
+    Access,
+    /// Tagged frames of many VLANs, for other bridges.
+    Trunk,
+}
+
+/// Point-to-point link adding a port to each of two bridges.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LinkDesc {
+    pub a: String,
+    pub b: String,
+    /// Time for a frame to cross the link (the default of scenario if not specified)
+    pub delay_ns: Option<u64>,
+    /// Path cost of the ports of the link in spanning tree
+    pub cost: Option<u32>,
+    /// VLANs carried by the trunk ports of the link (all if not specified), the default VLAN
+    /// being native
+    pub vlans: Option<Vec<u16>>,
+}
+
+/// Failure of a segment or link (named `a-b` after its bridges) at some time.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FailureDesc {
+    pub segment: String,
+    /// Time of failure since frames start being sent
+    pub at_ms: u64,
+    /// Time of recovery since frames start being sent, never if not specified
+    pub restore_ms: Option<u64>,
+}
+
+/// Path cost of 100 Mbit/s Ethernet in spanning tree.
+const DEFAULT_COST: u32 = 19;
+
+/// Segment of a resolved network.
+#[derive(Debug, Clone)]
+pub struct Lan {
+    pub name: String,
+    pub stations: bool,
+    pub delay_ns: u64,
+    pub cost: u32,
+    pub edge: bool,
+    /// Ports attached to the segment, as bridge and port index.
+    pub ports: Vec<(usize, usize)>,
+}
+
+/// VLANs of a port of a resolved network.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PortVlan {
+    /// Untagged frames of one VLAN.
+    Access(u16),
+    /// Tagged frames of allowed VLANs (all if `None`), untagged ones of the native VLAN.
+    Trunk { native: u16, allowed: Option<Vec<u16>> },
+}
+
+impl PortVlan {
+    /// Whether the port carries frames of a VLAN.
+    pub fn carries(&self, vid: u16) -> bool {
+        match self {
+            PortVlan::Access(vlan) => *vlan == vid,
+            PortVlan::Trunk { native, allowed } => {
+                vid == *native || allowed.as_ref().is_none_or(|allowed| allowed.contains(&vid))
+            }
+        }
+    }
+
+    /// VLAN of a frame received with a tag or not, or `None` if the port does not accept it.
+    pub fn ingress(&self, tag: Option<VlanTag>) -> Option<u16> {
+        let vid = match (self, tag) {
+            (PortVlan::Access(vlan), None) | (PortVlan::Trunk { native: vlan, .. }, None) => *vlan,
+            // priority tagged frames belong to the VLAN of the port
+            (PortVlan::Access(vlan), Some(VlanTag { vid: 0, .. }))
+            | (PortVlan::Trunk { native: vlan, .. }, Some(VlanTag { vid: 0, .. })) => *vlan,
+            (_, Some(tag)) => tag.vid,
+        };
+        self.carries(vid).then_some(vid)
+    }
+
+    /// Tag of a frame of a VLAN sent out of the port, untagged on access ports and for the native
+    /// VLAN of trunk ports.
+    pub fn egress(&self, vid: u16, pcp: u8) -> Option<VlanTag> {
+        match self {
+            PortVlan::Trunk { native, .. } if *native != vid => Some(VlanTag { vid, pcp }),
+            _ => None,
+        }
+    }
+}
+
+/// Failure of a segment of a resolved network.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub lan: usize,
+    pub at_ms: u64,
+    pub restore_ms: Option<u64>,
+}
+
+/// Network with names resolved to indices.
+#[derive(Debug, Clone)]
+pub struct Network {
+    pub lans: Vec<Lan>,
+    /// Names of bridges.
+    pub bridges: Vec<String>,
+    /// Segments attached to the ports of each bridge.
+    pub ports: Vec<Vec<usize>>,
+    /// VLANs of the ports of each bridge.
+    pub vlans: Vec<Vec<PortVlan>>,
+    pub priorities: Vec<u16>,
+    pub spanning_trees: Vec<SpanningTree>,
+    pub failures: Vec<Failure>,
+}
+
+impl Topology {
+    /// Load a topology from file, as JSON if its extension is `.json` or TOML otherwise.
+    pub fn load(path: &Path) -> Self {
+        let text = fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("cannot read topology {}: {}", path.display(), err));
+        if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&text)
+                .unwrap_or_else(|err| panic!("invalid topology {}: {}", path.display(), err))
+        } else {
+            toml::from_str(&text)
+                .unwrap_or_else(|err| panic!("invalid topology {}: {}", path.display(), err))
+        }
+    }
+
+    /// Resolve names, with specified delay and spanning tree protocol as defaults.
+    pub fn resolve(&self, delay_ns: u64, spanning_tree: SpanningTree) -> Network {
+        let mut lans = self.segments.iter()
+            .map(|s| Lan {
+                name: s.name.clone(),
+                stations: s.stations,
+                delay_ns: s.delay_ns.unwrap_or(delay_ns),
+                cost: s.cost.unwrap_or(DEFAULT_COST),
+                edge: false,
+                ports: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        let lan_index = lans.iter()
+            .enumerate()
+            .map(|(i, lan)| (lan.name.clone(), i))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(lan_index.len(), lans.len(), "duplicate segment names in topology");
+        let bridges = self.bridges.iter().map(|b| b.name.clone()).collect::<Vec<_>>();
+        let bridge_index = bridges.iter()
+            .enumerate()
+            .map(|(i, name)| (name.clone(), i))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(bridge_index.len(), bridges.len(), "duplicate bridge names in topology");
+        let mut ports = vec![Vec::new(); bridges.len()];
+        let mut vlans = vec![Vec::new(); bridges.len()];
+        for (i, bridge) in self.bridges.iter().enumerate() {
+            for port in &bridge.ports {
+                let name = port.segment();
+                let lan = *lan_index.get(name)
+                    .unwrap_or_else(|| panic!("unknown segment {} of bridge {}", name, bridge.name));
+                lans[lan].ports.push((i, ports[i].len()));
+                ports[i].push(lan);
+                vlans[i].push(port.vlan());
+            }
+        }
+        for link in &self.links {
+            let lan = lans.len();
+            lans.push(Lan {
+                name: format!("{}-{}", link.a, link.b),
+                stations: false,
+                delay_ns: link.delay_ns.unwrap_or(delay_ns),
+                cost: link.cost.unwrap_or(DEFAULT_COST),
+                edge: false,
+                ports: Vec::new(),
+            });
+            for name in [&link.a, &link.b] {
+                let i = *bridge_index.get(name)
+                    .unwrap_or_else(|| panic!("unknown bridge {} of link", name));
+                lans[lan].ports.push((i, ports[i].len()));
+                ports[i].push(lan);
+                vlans[i].push(PortVlan::Trunk { native: DEFAULT_VLAN, allowed: link.vlans.clone() });
+            }
+        }
+        for (lan, s) in lans.iter_mut().zip(&self.segments) {
+            lan.edge = s.edge.unwrap_or(s.stations && lan.ports.len() == 1);
+        }
+        let priorities = self.bridges.iter().map(|b| b.priority.unwrap_or(0x8000)).collect();
+        let spanning_trees = self.bridges.iter().map(|b| b.spanning_tree.unwrap_or(spanning_tree)).collect();
+        let failures = self.failures.iter()
+            .map(|f| Failure {
+                lan: lans.iter()
+                    .position(|lan| lan.name == f.segment)
+                    .unwrap_or_else(|| panic!("unknown segment {} of failure", f.segment)),
+                at_ms: f.at_ms,
+                restore_ms: f.restore_ms,
+            })
+            .collect();
+        Network { lans, bridges, ports, vlans, priorities, spanning_trees, failures }
+    }
+}
@@ -0,0 +1,390 @@
+use std::fmt::{Display, Formatter};
+use crate::{Address, Frame, FrameData, Segment, VlanTag};
+
+/// EtherType of IPv4.
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+/// EtherType of ARP.
+pub const ETHERTYPE_ARP: u16 = 0x0806;
+/// EtherType of IPv6.
+pub const ETHERTYPE_IPV6: u16 = 0x86dd;
+/// Tag protocol identifier of IEEE 802.1Q, in place of the EtherType of tagged frames.
+pub const ETHERTYPE_VLAN: u16 = 0x8100;
+/// EtherType for local experiments (IEEE 802), carried by frames of the simulation.
+pub const ETHERTYPE_EXPERIMENTAL: u16 = 0x88b5;
+
+/// Largest value of the type field that is a length of payload (IEEE 802.3) rather than an EtherType.
+pub const MAX_LENGTH: u16 = 1500;
+/// Smallest value of the type field that is an EtherType (Ethernet II).
+pub const MIN_ETHERTYPE: u16 = 0x0600;
+
+/// Least count of bytes of payload of an untagged frame, shorter ones being padded.
+pub const MIN_PAYLOAD: usize = 46;
+/// Most count of bytes of payload.
+pub const MAX_PAYLOAD: usize = 1500;
+/// Count of bytes of addresses and type field.
+pub const HEADER_LEN: usize = 14;
+/// Count of bytes of an IEEE 802.1Q tag.
+pub const TAG_LEN: usize = 4;
+/// Count of bytes of the frame check sequence.
+pub const FCS_LEN: usize = 4;
+/// Least count of bytes of a frame, from destination to frame check sequence.
+pub const MIN_FRAME: usize = HEADER_LEN + MIN_PAYLOAD + FCS_LEN;
+/// Most count of bytes of an untagged frame, from destination to frame check sequence.
+pub const MAX_FRAME: usize = HEADER_LEN + MAX_PAYLOAD + FCS_LEN;
+
+/// Lookup table of CRC-32 (IEEE 802.3, reflected polynomial `0xedb88320`), one entry per byte.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+            k += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 of IEEE 802.3 over some bytes, as used for the frame check sequence.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, b| (crc >> 8) ^ CRC_TABLE[((crc ^ *b as u32) & 0xff) as usize])
+}
+
+/// Reason a frame cannot be encoded or decoded.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CodecError {
+    /// Frame of fewer bytes than the least, with its count of bytes.
+    TooShort(usize),
+    /// Frame of more bytes than the most, with its count of bytes.
+    TooLong(usize),
+    /// Payload of more bytes than the most, with its count of bytes.
+    PayloadTooLong(usize),
+    /// Frame check sequence not matching the bytes of the frame.
+    BadFcs { expected: u32, found: u32 },
+    /// Length in the type field longer than the payload carried, or not matching the payload to encode.
+    BadLength { length: u16, payload: usize },
+    /// Type field neither a length nor an EtherType.
+    BadEtherType(u16),
+    /// VLAN identifier or priority out of range.
+    BadTag(VlanTag),
+}
+
+impl Display for CodecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodecError::TooShort(len) => write!(f, "frame of {} bytes, at least {} expected", len, MIN_FRAME),
+            CodecError::TooLong(len) => write!(f, "frame of {} bytes, too long", len),
+            CodecError::PayloadTooLong(len) => {
+                write!(f, "payload of {} bytes, at most {} expected", len, MAX_PAYLOAD)
+            }
+            CodecError::BadFcs { expected, found } => {
+                write!(f, "frame check sequence {:08x}, {:08x} expected", found, expected)
+            }
+            CodecError::BadLength { length, payload } => {
+                write!(f, "length {} for a payload of {} bytes", length, payload)
+            }
+            CodecError::BadEtherType(value) => write!(f, "type field {:04x} neither length nor EtherType", value),
+            CodecError::BadTag(tag) => write!(f, "VLAN tag {} out of range", tag),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+/// Ethernet II or IEEE 802.3 frame as it is on the wire, without preamble.
+///
+/// The type field is an EtherType from `MIN_ETHERTYPE` on (Ethernet II), or the length of the
+/// payload up to `MAX_LENGTH` (IEEE 802.3). Payloads shorter than the least are padded with zeros
+/// when encoded; padding is removed when decoded only if the type field tells the length.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct EthernetFrame {
+    pub dst: Address,
+    pub src: Address,
+    /// IEEE 802.1Q tag, whose drop eligible indicator is always clear.
+    pub vlan: Option<VlanTag>,
+    pub ether_type: u16,
+    pub payload: Vec<u8>,
+}
+
+impl EthernetFrame {
+    /// Ethernet II frame carrying a payload of an EtherType.
+    pub fn new(dst: Address, src: Address, ether_type: u16, payload: Vec<u8>) -> Self {
+        EthernetFrame { dst, src, vlan: None, ether_type, payload }
+    }
+
+    /// IEEE 802.3 frame, telling the length of its payload in place of an EtherType.
+    pub fn with_length(dst: Address, src: Address, payload: Vec<u8>) -> Self {
+        let ether_type = payload.len().min(u16::MAX as usize) as u16;
+        EthernetFrame { dst, src, vlan: None, ether_type, payload }
+    }
+
+    /// Tag the frame for a VLAN.
+    pub fn with_vlan(mut self, vlan: Option<VlanTag>) -> Self {
+        self.vlan = vlan;
+        self
+    }
+
+    /// Whether the type field is the length of the payload rather than an EtherType.
+    pub fn is_length(&self) -> bool {
+        self.ether_type <= MAX_LENGTH
+    }
+
+    /// Count of bytes of the header, tag included.
+    fn header_len(&self) -> usize {
+        HEADER_LEN + if self.vlan.is_some() { TAG_LEN } else { 0 }
+    }
+
+    /// Count of bytes of the encoded frame, padding and frame check sequence included.
+    pub fn wire_len(&self) -> usize {
+        (self.header_len() + self.payload.len() + FCS_LEN).max(MIN_FRAME)
+    }
+
+    /// Check that the frame can be encoded.
+    pub fn validate(&self) -> Result<(), CodecError> {
+        if self.payload.len() > MAX_PAYLOAD {
+            return Err(CodecError::PayloadTooLong(self.payload.len()));
+        }
+        if self.is_length() && self.ether_type as usize != self.payload.len() {
+            return Err(CodecError::BadLength { length: self.ether_type, payload: self.payload.len() });
+        }
+        if !self.is_length() && self.ether_type < MIN_ETHERTYPE {
+            return Err(CodecError::BadEtherType(self.ether_type));
+        }
+        match self.vlan {
+            Some(tag) if tag.vid > 0xfff || tag.pcp > 7 => Err(CodecError::BadTag(tag)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Encode the frame into bytes, padded to the least length and ending with the frame check
+    /// sequence.
+    pub fn encode(&self) -> Result<Vec<u8>, CodecError> {
+        self.validate()?;
+        let mut bytes = Vec::with_capacity(self.wire_len());
+        bytes.extend_from_slice(&self.dst.data);
+        bytes.extend_from_slice(&self.src.data);
+        if let Some(tag) = self.vlan {
+            bytes.extend_from_slice(&ETHERTYPE_VLAN.to_be_bytes());
+            bytes.extend_from_slice(&((tag.pcp as u16) << 13 | tag.vid).to_be_bytes());
+        }
+        bytes.extend_from_slice(&self.ether_type.to_be_bytes());
+        bytes.extend_from_slice(&self.payload);
+        bytes.resize(bytes.len().max(MIN_FRAME - FCS_LEN), 0);
+        // transmitted least significant byte first
+        let fcs = crc32(&bytes);
+        bytes.extend_from_slice(&fcs.to_le_bytes());
+        Ok(bytes)
+    }
+
+    /// Decode a frame from bytes, checking its length and frame check sequence.
+    pub fn decode(bytes: &[u8]) -> Result<Self, CodecError> {
+        if bytes.len() < MIN_FRAME {
+            return Err(CodecError::TooShort(bytes.len()));
+        }
+        let tagged = bytes[12..14] == ETHERTYPE_VLAN.to_be_bytes();
+        let max = MAX_FRAME + if tagged { TAG_LEN } else { 0 };
+        if bytes.len() > max {
+            return Err(CodecError::TooLong(bytes.len()));
+        }
+        let (body, fcs) = bytes.split_at(bytes.len() - FCS_LEN);
+        let found = u32::from_le_bytes(fcs.try_into().unwrap());
+        let expected = crc32(body);
+        if found != expected {
+            return Err(CodecError::BadFcs { expected, found });
+        }
+        let dst = Address { data: body[0..6].try_into().unwrap() };
+        let src = Address { data: body[6..12].try_into().unwrap() };
+        let (vlan, rest) = if tagged {
+            let tci = u16::from_be_bytes([body[14], body[15]]);
+            (Some(VlanTag { vid: tci & 0xfff, pcp: (tci >> 13) as u8 }), &body[16..])
+        } else {
+            (None, &body[12..])
+        };
+        let ether_type = u16::from_be_bytes([rest[0], rest[1]]);
+        let payload = &rest[2..];
+        let payload = if ether_type <= MAX_LENGTH {
+            // the rest is padding
+            if ether_type as usize > payload.len() {
+                return Err(CodecError::BadLength { length: ether_type, payload: payload.len() });
+            }
+            &payload[..ether_type as usize]
+        } else if ether_type < MIN_ETHERTYPE {
+            return Err(CodecError::BadEtherType(ether_type));
+        } else {
+            payload
+        };
+        Ok(EthernetFrame { dst, src, vlan, ether_type, payload: payload.to_vec() })
+    }
+
+    /// Frame of the simulation carried by this frame, received on a segment.
+    ///
+    /// Returns `None` unless the frame is of `ETHERTYPE_EXPERIMENTAL` with the data in front of the
+    /// payload.
+    pub fn to_frame(&self, src_seg: Segment) -> Option<Frame> {
+        if self.ether_type != ETHERTYPE_EXPERIMENTAL {
+            return None;
+        }
+        let data = FrameData::try_from(self.payload.get(..size_of::<FrameData>())?).ok()?;
+        Some(Frame { src: self.src, src_seg, dst: self.dst, data, vlan: self.vlan })
+    }
+}
+
+impl From<&Frame> for EthernetFrame {
+    /// Frame on the wire for a frame of the simulation, carrying its data as the payload of
+    /// `ETHERTYPE_EXPERIMENTAL`.
+    fn from(frame: &Frame) -> Self {
+        EthernetFrame::new(frame.dst, frame.src, ETHERTYPE_EXPERIMENTAL, frame.data.to_vec()).with_vlan(frame.vlan)
+    }
+}
+
+impl Frame {
+    /// Bytes of the frame on the wire, from destination to frame check sequence.
+    pub fn encode(&self) -> Result<Vec<u8>, CodecError> {
+        EthernetFrame::from(self).encode()
+    }
+
+    /// Count of bytes of the frame on the wire, from destination to frame check sequence.
+    pub fn wire_len(&self) -> usize {
+        EthernetFrame::from(self).wire_len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DST: Address = Address { data: [0x02, 0x00, 0x00, 0x00, 0x00, 0x01] };
+    const SRC: Address = Address { data: [0x02, 0x00, 0x00, 0x00, 0x00, 0x02] };
+
+    /// Append the frame check sequence to bytes from destination to padding.
+    fn with_fcs(mut body: Vec<u8>) -> Vec<u8> {
+        let fcs = crc32(&body);
+        body.extend_from_slice(&fcs.to_le_bytes());
+        body
+    }
+
+    #[test]
+    fn crc32_known_answers() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"a"), 0xe8b7be43);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(crc32(b"The quick brown fox jumps over the lazy dog"), 0x414fa339);
+    }
+
+    #[test]
+    fn fcs_leaves_residue() {
+        // CRC-32 over a frame with its check sequence is the same constant for every frame
+        let bytes = EthernetFrame::new(DST, SRC, ETHERTYPE_IPV4, vec![0xab; 100]).encode().unwrap();
+        assert_eq!(crc32(&bytes), 0x2144df1c);
+    }
+
+    #[test]
+    fn ethernet_ii_round_trip() {
+        let frame = EthernetFrame::new(DST, SRC, ETHERTYPE_IPV6, (0..200).map(|i| i as u8).collect());
+        let bytes = frame.encode().unwrap();
+        assert_eq!(bytes.len(), HEADER_LEN + 200 + FCS_LEN);
+        assert_eq!(bytes.len(), frame.wire_len());
+        assert_eq!(bytes[0..6], DST.data);
+        assert_eq!(bytes[6..12], SRC.data);
+        assert_eq!(bytes[12..14], [0x86, 0xdd]);
+        assert_eq!(EthernetFrame::decode(&bytes), Ok(frame));
+    }
+
+    #[test]
+    fn length_round_trip_without_padding() {
+        let frame = EthernetFrame::with_length(DST, SRC, vec![1, 2, 3, 4, 5]);
+        assert!(frame.is_length());
+        let bytes = frame.encode().unwrap();
+        assert_eq!(bytes.len(), MIN_FRAME);
+        assert_eq!(bytes[12..14], [0x00, 0x05]);
+        assert_eq!(EthernetFrame::decode(&bytes), Ok(frame));
+    }
+
+    #[test]
+    fn ethernet_ii_keeps_padding() {
+        let frame = EthernetFrame::new(DST, SRC, ETHERTYPE_ARP, vec![0xff; 28]);
+        let bytes = frame.encode().unwrap();
+        assert_eq!(bytes.len(), MIN_FRAME);
+        assert!(bytes[HEADER_LEN + 28..MIN_FRAME - FCS_LEN].iter().all(|b| *b == 0));
+        let decoded = EthernetFrame::decode(&bytes).unwrap();
+        assert_eq!(decoded.payload.len(), MIN_PAYLOAD);
+        assert_eq!(decoded.payload[..28], frame.payload[..]);
+    }
+
+    #[test]
+    fn tagged_round_trip() {
+        let tag = VlanTag { vid: 0xabc, pcp: 5 };
+        let frame = EthernetFrame::new(DST, SRC, ETHERTYPE_IPV4, vec![7; MAX_PAYLOAD]).with_vlan(Some(tag));
+        let bytes = frame.encode().unwrap();
+        assert_eq!(bytes.len(), MAX_FRAME + TAG_LEN);
+        assert_eq!(bytes[12..18], [0x81, 0x00, 0xaa, 0xbc, 0x08, 0x00]);
+        assert_eq!(EthernetFrame::decode(&bytes), Ok(frame));
+    }
+
+    #[test]
+    fn simulation_frame_round_trip() {
+        let seg = Segment { data: [0x12, 0x34] };
+        for vlan in [None, Some(VlanTag { vid: 10, pcp: 3 })] {
+            let frame = Frame { src: SRC, src_seg: seg, dst: DST, data: [1, 2, 3, 4], vlan };
+            let bytes = frame.encode().unwrap();
+            assert_eq!(bytes.len(), frame.wire_len());
+            let decoded = EthernetFrame::decode(&bytes).unwrap();
+            assert_eq!(decoded.ether_type, ETHERTYPE_EXPERIMENTAL);
+            assert_eq!(decoded.to_frame(seg), Some(frame));
+        }
+        let other = EthernetFrame::new(DST, SRC, ETHERTYPE_IPV4, vec![1, 2, 3, 4]);
+        assert_eq!(other.to_frame(seg), None);
+        let short = EthernetFrame::new(DST, SRC, ETHERTYPE_EXPERIMENTAL, vec![1, 2, 3]);
+        assert_eq!(short.to_frame(seg), None);
+    }
+
+    #[test]
+    fn encode_errors() {
+        let frame = EthernetFrame::new(DST, SRC, ETHERTYPE_IPV4, vec![0; MAX_PAYLOAD + 1]);
+        assert_eq!(frame.encode(), Err(CodecError::PayloadTooLong(MAX_PAYLOAD + 1)));
+        let frame = EthernetFrame::new(DST, SRC, 10, vec![0; 5]);
+        assert_eq!(frame.encode(), Err(CodecError::BadLength { length: 10, payload: 5 }));
+        let frame = EthernetFrame::new(DST, SRC, 0x05f0, vec![0; 5]);
+        assert_eq!(frame.encode(), Err(CodecError::BadEtherType(0x05f0)));
+        for tag in [VlanTag { vid: 0x1000, pcp: 0 }, VlanTag { vid: 1, pcp: 8 }] {
+            let frame = EthernetFrame::new(DST, SRC, ETHERTYPE_IPV4, vec![0; 5]).with_vlan(Some(tag));
+            assert_eq!(frame.encode(), Err(CodecError::BadTag(tag)));
+        }
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(EthernetFrame::decode(&[0; MIN_FRAME - 1]), Err(CodecError::TooShort(MIN_FRAME - 1)));
+        let frame = EthernetFrame::new(DST, SRC, ETHERTYPE_IPV4, vec![0; MAX_PAYLOAD]);
+        let mut bytes = frame.encode().unwrap();
+        bytes.push(0);
+        assert_eq!(EthernetFrame::decode(&bytes), Err(CodecError::TooLong(MAX_FRAME + 1)));
+
+        let mut bytes = EthernetFrame::new(DST, SRC, ETHERTYPE_IPV4, vec![0; 50]).encode().unwrap();
+        bytes[20] ^= 1;
+        let found = u32::from_le_bytes(bytes[bytes.len() - FCS_LEN..].try_into().unwrap());
+        let expected = crc32(&bytes[..bytes.len() - FCS_LEN]);
+        assert_eq!(EthernetFrame::decode(&bytes), Err(CodecError::BadFcs { expected, found }));
+
+        let mut body = [DST.data, SRC.data].concat();
+        body.extend_from_slice(&100u16.to_be_bytes());
+        body.resize(MIN_FRAME - FCS_LEN, 0);
+        let bytes = with_fcs(body.clone());
+        assert_eq!(EthernetFrame::decode(&bytes), Err(CodecError::BadLength { length: 100, payload: MIN_PAYLOAD }));
+
+        body[12..14].copy_from_slice(&0x05f0u16.to_be_bytes());
+        assert_eq!(EthernetFrame::decode(&with_fcs(body)), Err(CodecError::BadEtherType(0x05f0)));
+    }
+
+    #[test]
+    fn errors_display() {
+        assert_eq!(CodecError::TooShort(10).to_string(), "frame of 10 bytes, at least 64 expected");
+        assert_eq!(CodecError::BadFcs { expected: 1, found: 2 }.to_string(),
+                   "frame check sequence 00000002, 00000001 expected");
+        assert_eq!(CodecError::BadTag(VlanTag { vid: 4096, pcp: 0 }).to_string(), "VLAN tag 4096/0 out of range");
+    }
+}
@@ -0,0 +1,435 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use serde::{Serialize, Deserialize};
+use crate::{Address, Segment};
+
+/// Strategy to choose the entry to evict when the forwarding database is full.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EvictionPolicy {
+    /// Evict the entry least recently looked up or learned.
+    Lru,
+    /// Evict the entry learned earliest.
+    Fifo,
+    /// Evict a random entry.
+    Random,
+    /// Evict the entry least frequently looked up or learned, least recently used among ties.
+    Lfu,
+}
+
+impl TryFrom<&str> for EvictionPolicy {
+    type Error = UnknownPolicy;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "lru" => Ok(EvictionPolicy::Lru),
+            "fifo" => Ok(EvictionPolicy::Fifo),
+            "random" => Ok(EvictionPolicy::Random),
+            "lfu" => Ok(EvictionPolicy::Lfu),
+            _ => Err(UnknownPolicy(value.to_string())),
+        }
+    }
+}
+
+/// Name that is not one of an eviction policy.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UnknownPolicy(pub String);
+
+impl Display for UnknownPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown eviction policy {:?}, expected lru, fifo, random or lfu", self.0)
+    }
+}
+
+impl std::error::Error for UnknownPolicy {}
+
+/// Counters of the forwarding database since its creation.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct FdbStats {
+    /// Lookups that found a live entry.
+    pub hits: usize,
+    /// Lookups that found no entry or an expired one.
+    pub misses: usize,
+    /// Entries evicted to make room for new ones.
+    pub evicted: usize,
+    /// Entries removed for being too old, by sweeping or to make room for new ones.
+    pub aged: usize,
+}
+
+impl FdbStats {
+    /// Ratio of hits in all lookups.
+    pub fn hit_rate(&self) -> f64 {
+        self.hits as f64 / (self.hits + self.misses).max(1) as f64
+    }
+}
+
+/// Learned entry of the forwarding database.
+struct Entry<V> {
+    segment: V,
+    /// Timestamp of the last frame from the address, in microseconds.
+    last_seen: u64,
+    /// Key of the entry in the eviction order.
+    rank: (u64, u64),
+    uses: u64,
+}
+
+/// Forwarding database of a learning bridge, with aging and limited capacity like a CAM table.
+///
+/// Addresses are learned on segments, or anything else a bridge forwards to such as its ports.
+/// Timestamps are in microseconds from any fixed origin.
+pub struct ForwardingDatabase<V = Segment> {
+    map: HashMap<Address, Entry<V>>,
+    /// Entries in order of eviction, the first to be evicted first.
+    order: BTreeMap<(u64, u64), Address>,
+    /// Entries in order of the time last seen, the first to expire first.
+    seen: BTreeSet<(u64, Address)>,
+    capacity: usize,
+    aging: u64,
+    policy: EvictionPolicy,
+    rng: fastrand::Rng,
+    tick: u64,
+    stats: FdbStats,
+}
+
+impl<V: Copy + Eq> ForwardingDatabase<V> {
+    pub fn new(capacity: usize, aging: u64, policy: EvictionPolicy) -> Self {
+        ForwardingDatabase {
+            map: HashMap::with_capacity(capacity.min(1 << 16)),
+            order: BTreeMap::new(),
+            seen: BTreeSet::new(),
+            capacity,
+            aging,
+            policy,
+            rng: fastrand::Rng::new(),
+            tick: 0,
+            stats: FdbStats::default(),
+        }
+    }
+
+    /// Seed the source of randomness used by random eviction.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = fastrand::Rng::with_seed(seed);
+        self
+    }
+
+    /// Position of an entry in eviction order after being used.
+    fn rank(&mut self, prev: Option<(u64, u64)>, uses: u64) -> (u64, u64) {
+        self.tick += 1;
+        match (self.policy, prev) {
+            (EvictionPolicy::Lru, _) => (self.tick, 0),
+            (EvictionPolicy::Fifo | EvictionPolicy::Random, Some(prev)) => prev,
+            (EvictionPolicy::Fifo, None) => (self.tick, 0),
+            (EvictionPolicy::Random, None) => (self.rng.u64(..), self.tick),
+            (EvictionPolicy::Lfu, _) => (uses, self.tick),
+        }
+    }
+
+    /// Move an entry to its new position in eviction order.
+    fn touch(&mut self, addr: Address) {
+        let (prev, uses) = {
+            let entry = self.map.get_mut(&addr).unwrap();
+            entry.uses += 1;
+            (entry.rank, entry.uses)
+        };
+        let rank = self.rank(Some(prev), uses);
+        if rank != prev {
+            self.order.remove(&prev);
+            self.order.insert(rank, addr);
+            self.map.get_mut(&addr).unwrap().rank = rank;
+        }
+    }
+
+    fn remove(&mut self, addr: &Address) {
+        if let Some(entry) = self.map.remove(addr) {
+            self.order.remove(&entry.rank);
+            self.seen.remove(&(entry.last_seen, *addr));
+        }
+    }
+
+    fn expired(&self, entry: &Entry<V>, now: u64) -> bool {
+        now.saturating_sub(entry.last_seen) > self.aging
+    }
+
+    /// Learn the segment of an address, or refresh it if already known.
+    ///
+    /// Returns `true` if the address was known on another segment, i.e. the station has moved.
+    pub fn learn(&mut self, addr: Address, segment: V, now: u64) -> bool {
+        if let Some(entry) = self.map.get_mut(&addr) {
+            let moved = entry.segment != segment;
+            entry.segment = segment;
+            self.seen.remove(&(entry.last_seen, addr));
+            self.seen.insert((now, addr));
+            entry.last_seen = now;
+            self.touch(addr);
+            return moved;
+        }
+        if self.capacity == 0 {
+            return false;
+        }
+        if self.map.len() >= self.capacity {
+            // an expired entry makes room before any live one is evicted
+            match self.seen.first() {
+                Some(&(last_seen, oldest)) if now.saturating_sub(last_seen) > self.aging => {
+                    self.remove(&oldest);
+                    self.stats.aged += 1;
+                }
+                _ => {
+                    let victim = *self.order.first_key_value().unwrap().1;
+                    self.remove(&victim);
+                    self.stats.evicted += 1;
+                }
+            }
+        }
+        let rank = self.rank(None, 1);
+        self.order.insert(rank, addr);
+        self.seen.insert((now, addr));
+        self.map.insert(addr, Entry { segment, last_seen: now, rank, uses: 1 });
+        false
+    }
+
+    /// Look up the segment of an address, treating expired entries as unknown.
+    pub fn lookup(&mut self, addr: &Address, now: u64) -> Option<V> {
+        match self.map.get(addr) {
+            Some(entry) if !self.expired(entry, now) => {
+                let segment = entry.segment;
+                self.touch(*addr);
+                self.stats.hits += 1;
+                Some(segment)
+            }
+            _ => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Remove all expired entries and return the count of them.
+    pub fn sweep(&mut self, now: u64) -> usize {
+        let expired = self.map.iter()
+            .filter(|(_, entry)| self.expired(entry, now))
+            .map(|(addr, _)| *addr)
+            .collect::<Vec<_>>();
+        for addr in &expired {
+            self.remove(addr);
+        }
+        self.stats.aged += expired.len();
+        expired.len()
+    }
+
+    /// Remove all entries learned on a segment and return the count of them.
+    pub fn forget(&mut self, segment: V) -> usize {
+        let learned = self.map.iter()
+            .filter(|(_, entry)| entry.segment == segment)
+            .map(|(addr, _)| *addr)
+            .collect::<Vec<_>>();
+        for addr in &learned {
+            self.remove(addr);
+        }
+        learned.len()
+    }
+
+    /// Change the aging time, e.g. to age out faster while the topology is changing.
+    pub fn set_aging(&mut self, aging: u64) {
+        self.aging = aging;
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn aging(&self) -> u64 {
+        self.aging
+    }
+
+    pub fn policy(&self) -> EvictionPolicy {
+        self.policy
+    }
+
+    pub fn stats(&self) -> FdbStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(n: u8) -> Address {
+        Address { data: [0x02, 0, 0, 0, 0, n] }
+    }
+
+    fn seg(n: u8) -> Segment {
+        Segment { data: [0, n] }
+    }
+
+    /// Database of 3 entries holding addresses 1 to 3, learned in order at times 0 to 2.
+    fn full(policy: EvictionPolicy) -> ForwardingDatabase {
+        let mut fdb = ForwardingDatabase::new(3, 1000, policy).with_seed(7);
+        for n in 1..=3 {
+            fdb.learn(addr(n), seg(n), n as u64 - 1);
+        }
+        fdb
+    }
+
+    /// Addresses 1 to 4 still known after learning address 4.
+    fn survivors(mut fdb: ForwardingDatabase) -> Vec<u8> {
+        fdb.learn(addr(4), seg(4), 10);
+        (1..=4).filter(|n| fdb.lookup(&addr(*n), 10).is_some()).collect()
+    }
+
+    #[test]
+    fn policy_from_name() {
+        assert_eq!(EvictionPolicy::try_from("lru"), Ok(EvictionPolicy::Lru));
+        assert_eq!(EvictionPolicy::try_from("fifo"), Ok(EvictionPolicy::Fifo));
+        assert_eq!(EvictionPolicy::try_from("random"), Ok(EvictionPolicy::Random));
+        assert_eq!(EvictionPolicy::try_from("lfu"), Ok(EvictionPolicy::Lfu));
+        let err = EvictionPolicy::try_from("mru").unwrap_err();
+        assert_eq!(err, UnknownPolicy("mru".to_string()));
+        assert_eq!(err.to_string(), "unknown eviction policy \"mru\", expected lru, fifo, random or lfu");
+    }
+
+    #[test]
+    fn capacity_bounds_entries() {
+        for policy in [EvictionPolicy::Lru, EvictionPolicy::Fifo, EvictionPolicy::Random, EvictionPolicy::Lfu] {
+            let mut fdb = ForwardingDatabase::new(3, 1000, policy);
+            for n in 1..=10 {
+                fdb.learn(addr(n), seg(n), 0);
+                assert!(fdb.len() <= 3, "{:?}", policy);
+            }
+            assert_eq!(fdb.len(), 3);
+            assert_eq!(fdb.stats().evicted, 7);
+            // the last one learned is never the one evicted
+            assert_eq!(fdb.lookup(&addr(10), 0), Some(seg(10)));
+        }
+    }
+
+    #[test]
+    fn expired_entry_makes_room_first() {
+        for policy in [EvictionPolicy::Lru, EvictionPolicy::Fifo, EvictionPolicy::Random, EvictionPolicy::Lfu] {
+            let mut fdb = full(policy);
+            // address 1 refreshed, 2 and 3 expired but not swept yet
+            fdb.learn(addr(1), seg(1), 1500);
+            fdb.learn(addr(4), seg(4), 1502);
+            assert_eq!(fdb.len(), 3, "{:?}", policy);
+            assert_eq!((fdb.stats().aged, fdb.stats().evicted), (1, 0), "{:?}", policy);
+            assert_eq!(fdb.lookup(&addr(1), 1502), Some(seg(1)), "{:?}", policy);
+            assert_eq!(fdb.lookup(&addr(4), 1502), Some(seg(4)), "{:?}", policy);
+            // the one seen last of the two expired is still there for sweeping
+            assert_eq!(fdb.sweep(1502), 1, "{:?}", policy);
+            assert_eq!(fdb.stats().aged, 2);
+        }
+    }
+
+    #[test]
+    fn zero_capacity_learns_nothing() {
+        let mut fdb = ForwardingDatabase::<Segment>::new(0, 1000, EvictionPolicy::Lru);
+        assert!(!fdb.learn(addr(1), seg(1), 0));
+        assert!(fdb.is_empty());
+        assert_eq!(fdb.lookup(&addr(1), 0), None);
+    }
+
+    #[test]
+    fn learn_tells_moves() {
+        let mut fdb = ForwardingDatabase::new(8, 1000, EvictionPolicy::Lru);
+        assert!(!fdb.learn(addr(1), seg(1), 0));
+        assert!(!fdb.learn(addr(1), seg(1), 1));
+        assert!(fdb.learn(addr(1), seg(2), 2));
+        assert_eq!(fdb.lookup(&addr(1), 2), Some(seg(2)));
+        assert_eq!(fdb.len(), 1);
+    }
+
+    #[test]
+    fn entries_age_out() {
+        let mut fdb = ForwardingDatabase::new(8, 100, EvictionPolicy::Lru);
+        fdb.learn(addr(1), seg(1), 0);
+        fdb.learn(addr(2), seg(2), 50);
+        // at the aging time the entry is still alive, just after it is not
+        assert_eq!(fdb.lookup(&addr(1), 100), Some(seg(1)));
+        assert_eq!(fdb.lookup(&addr(1), 101), None);
+        assert_eq!(fdb.len(), 2);
+        assert_eq!(fdb.sweep(101), 1);
+        assert_eq!(fdb.len(), 1);
+        // learning again refreshes the entry, looking it up does not
+        fdb.learn(addr(2), seg(2), 140);
+        assert_eq!(fdb.sweep(200), 0);
+        assert_eq!(fdb.lookup(&addr(2), 240), Some(seg(2)));
+        assert_eq!(fdb.sweep(241), 1);
+        assert!(fdb.is_empty());
+        let stats = fdb.stats();
+        assert_eq!((stats.hits, stats.misses, stats.aged), (2, 1, 2));
+    }
+
+    #[test]
+    fn set_aging_applies_to_known_entries() {
+        let mut fdb = ForwardingDatabase::new(8, 1000, EvictionPolicy::Lru);
+        fdb.learn(addr(1), seg(1), 0);
+        fdb.set_aging(10);
+        assert_eq!(fdb.aging(), 10);
+        assert_eq!(fdb.lookup(&addr(1), 11), None);
+    }
+
+    #[test]
+    fn lru_evicts_least_recently_used() {
+        let mut fdb = full(EvictionPolicy::Lru);
+        fdb.lookup(&addr(1), 5);
+        assert_eq!(survivors(fdb), vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn fifo_evicts_first_learned() {
+        let mut fdb = full(EvictionPolicy::Fifo);
+        fdb.lookup(&addr(1), 5);
+        fdb.learn(addr(1), seg(1), 6);
+        assert_eq!(survivors(fdb), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn lfu_evicts_least_frequently_used() {
+        let mut fdb = full(EvictionPolicy::Lfu);
+        fdb.lookup(&addr(1), 5);
+        fdb.lookup(&addr(1), 5);
+        fdb.lookup(&addr(3), 5);
+        assert_eq!(survivors(fdb), vec![1, 3, 4]);
+        // least recently used among ties
+        let mut fdb = full(EvictionPolicy::Lfu);
+        fdb.lookup(&addr(1), 5);
+        fdb.lookup(&addr(2), 5);
+        fdb.lookup(&addr(3), 5);
+        assert_eq!(survivors(fdb), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn random_evicts_by_seed() {
+        let victim = |seed| {
+            let mut fdb = ForwardingDatabase::new(3, 1000, EvictionPolicy::Random).with_seed(seed);
+            for n in 1..=4 {
+                fdb.learn(addr(n), seg(n), 0);
+            }
+            (1..=3).find(|n| fdb.lookup(&addr(*n), 10).is_none()).unwrap()
+        };
+        let victims = (0..64).map(victim).collect::<Vec<_>>();
+        assert_eq!(victims, (0..64).map(victim).collect::<Vec<_>>());
+        for n in 1..=3 {
+            assert!(victims.contains(&n), "address {} never evicted", n);
+        }
+    }
+
+    #[test]
+    fn forget_removes_segment() {
+        let mut fdb = ForwardingDatabase::new(8, 1000, EvictionPolicy::Lru);
+        fdb.learn(addr(1), seg(1), 0);
+        fdb.learn(addr(2), seg(2), 0);
+        fdb.learn(addr(3), seg(1), 0);
+        assert_eq!(fdb.forget(seg(1)), 2);
+        assert_eq!(fdb.len(), 1);
+        assert_eq!(fdb.lookup(&addr(2), 0), Some(seg(2)));
+    }
+}
@@ -1,35 +1,216 @@
 use std::fmt::{Display, Formatter};
-use serde::{Serialize, Deserialize};
+use std::str::FromStr;
+use serde::{Serialize, Deserialize, Deserializer};
+use serde::de::Error;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub mod ethernet;
+pub mod fdb;
+pub mod stp;
+pub mod stream;
+pub mod trace;
+
+/// Reason a text cannot be parsed, with positions in bytes from the beginning of the text.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    /// Wrong count of digits, bytes or fields, with the count found and the count expected.
+    WrongLength { found: usize, expected: usize },
+    /// Character that is not a hexadecimal digit, at its position.
+    BadHexDigit(usize),
+    /// Field missing at the end of the text, by name.
+    MissingField(&'static str),
+    /// Field not of a valid value, by name, at its position.
+    BadField(&'static str, usize),
+    /// Text left after the last field, from its position.
+    TrailingGarbage(usize),
+}
+
+impl ParseError {
+    /// Same error in a text embedding the parsed one at specified position.
+    fn at(self, pos: usize) -> Self {
+        match self {
+            ParseError::BadHexDigit(p) => ParseError::BadHexDigit(pos + p),
+            ParseError::BadField(name, p) => ParseError::BadField(name, pos + p),
+            ParseError::TrailingGarbage(p) => ParseError::TrailingGarbage(pos + p),
+            err => err,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::WrongLength { found, expected } => write!(f, "{} found where {} expected", found, expected),
+            ParseError::BadHexDigit(pos) => write!(f, "not a hexadecimal digit at {}", pos),
+            ParseError::MissingField(name) => write!(f, "missing {}", name),
+            ParseError::BadField(name, pos) => write!(f, "invalid {} at {}", name, pos),
+            ParseError::TrailingGarbage(pos) => write!(f, "trailing characters from {}", pos),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse bytes written as a group of `digits` hexadecimal digits, two per byte.
+fn parse_hex(group: &str, digits: usize) -> Result<Vec<u8>, ParseError> {
+    if let Some((pos, _)) = group.char_indices().find(|(_, c)| !c.is_ascii_hexdigit()) {
+        return Err(ParseError::BadHexDigit(pos));
+    }
+    if group.len() != digits {
+        return Err(ParseError::WrongLength { found: group.len(), expected: digits });
+    }
+    Ok((0..digits).step_by(2).map(|k| u8::from_str_radix(&group[k..k + 2], 16).unwrap()).collect())
+}
+
+/// Parse bytes written as groups of `digits` hexadecimal digits separated by `sep`.
+fn parse_hex_groups(s: &str, sep: char, digits: usize) -> Result<Vec<u8>, ParseError> {
+    let mut bytes = Vec::new();
+    let mut pos = 0;
+    for group in s.split(sep) {
+        bytes.extend(parse_hex(group, digits).map_err(|err| err.at(pos))?);
+        pos += group.len() + sep.len_utf8();
+    }
+    Ok(bytes)
+}
+
+/// Fields of a line separated by single spaces, taken in order with their positions.
+struct Fields<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn new(text: &'a str) -> Self {
+        Fields { text, pos: 0 }
+    }
+
+    /// Take the next field with its position, naming it if missing.
+    fn next(&mut self, name: &'static str) -> Result<(&'a str, usize), ParseError> {
+        if self.pos >= self.text.len() {
+            return Err(ParseError::MissingField(name));
+        }
+        let pos = self.pos;
+        let field = self.text[pos..].split(' ').next().unwrap();
+        self.pos += field.len() + 1;
+        Ok((field, pos))
+    }
+
+    /// Parse the next field, naming it if missing.
+    fn parse<T: FromStr<Err = ParseError>>(&mut self, name: &'static str) -> Result<T, ParseError> {
+        let (field, pos) = self.next(name)?;
+        field.parse().map_err(|err: ParseError| err.at(pos))
+    }
+
+    /// Take the next field if any.
+    fn optional(&mut self) -> Option<(&'a str, usize)> {
+        self.next("").ok()
+    }
+
+    /// Check that no field is left.
+    fn end(self) -> Result<(), ParseError> {
+        match self.pos <= self.text.len() {
+            true => Err(ParseError::TrailingGarbage(self.pos - 1)),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Physical (MAC-48) address of a station.
+///
+/// Serialised as its six bytes. Datasets from before addresses were six bytes long hold four, which
+/// are read as locally administered addresses `02:00:aa:bb:cc:dd`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
 #[repr(transparent)]
 pub struct Address {
-    pub data: [u8; 4]
+    pub data: [u8; 6]
+}
+
+impl Address {
+    /// Address of all stations.
+    pub const BROADCAST: Address = Address { data: [0xff; 6] };
+
+    /// Address of a station from a four-byte address of old datasets.
+    pub fn from_legacy(data: [u8; 4]) -> Self {
+        let [a, b, c, d] = data;
+        Address { data: [0x02, 0x00, a, b, c, d] }
+    }
+
+    /// Whether the address is of a single station.
+    pub fn is_unicast(&self) -> bool {
+        self.data[0] & 0x01 == 0
+    }
+
+    /// Whether the address is of a group of stations, broadcast included.
+    pub fn is_multicast(&self) -> bool {
+        !self.is_unicast()
+    }
+
+    /// Whether the address is of all stations.
+    pub fn is_broadcast(&self) -> bool {
+        *self == Address::BROADCAST
+    }
+
+    /// Whether the address is assigned locally rather than by the manufacturer.
+    pub fn is_local(&self) -> bool {
+        self.data[0] & 0x02 != 0
+    }
+
+    /// Organisationally unique identifier, the first three bytes.
+    pub fn oui(&self) -> [u8; 3] {
+        [self.data[0], self.data[1], self.data[2]]
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "Address")]
+        struct Raw {
+            data: Vec<u8>,
+        }
+
+        let raw = Raw::deserialize(deserializer)?;
+        match raw.data.len() {
+            6 => Ok(Address { data: raw.data.try_into().unwrap() }),
+            4 => Ok(Address::from_legacy(raw.data.try_into().unwrap())),
+            len => Err(D::Error::invalid_length(len, &"6 bytes, or 4 of old datasets")),
+        }
+    }
 }
 
 impl Display for Address {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let a1 = self.data[0];
-        let a2 = self.data[1];
-        let a3 = self.data[2];
-        let a4 = self.data[3];
-        write!(f, "{a1:02x}:{a2:02x}:{a3:02x}:{a4:02x}")
+        let [a1, a2, a3, a4, a5, a6] = self.data;
+        write!(f, "{a1:02x}:{a2:02x}:{a3:02x}:{a4:02x}:{a5:02x}:{a6:02x}")
+    }
+}
+
+impl FromStr for Address {
+    type Err = ParseError;
+
+    /// Parse an address as `aa:bb:cc:dd:ee:ff`, `aa-bb-cc-dd-ee-ff` or `aabb.ccdd.eeff` (Cisco), or
+    /// `aa:bb:cc:dd` of old datasets.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = if s.contains('.') {
+            // three groups of four digits
+            parse_hex_groups(s, '.', 4)?
+        } else if s.contains('-') {
+            parse_hex_groups(s, '-', 2)?
+        } else {
+            parse_hex_groups(s, ':', 2)?
+        };
+        match bytes.len() {
+            6 => Ok(Address { data: bytes.try_into().unwrap() }),
+            4 if !s.contains(['.', '-']) => Ok(Address::from_legacy(bytes.try_into().unwrap())),
+            len => Err(ParseError::WrongLength { found: len, expected: 6 }),
+        }
     }
 }
 
 impl TryFrom<&str> for Address {
-    type Error = ();
+    type Error = ParseError;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        if value.len() != 11 {
-            return Err(());
-        }
-        let mut data = [0; 4];
-        data[0] = u8::from_str_radix(&value[0..2], 16).map_err(|_| ())?;
-        data[1] = u8::from_str_radix(&value[3..5], 16).map_err(|_| ())?;
-        data[2] = u8::from_str_radix(&value[6..8], 16).map_err(|_| ())?;
-        data[3] = u8::from_str_radix(&value[9..11], 16).map_err(|_| ())?;
-        Ok(Address { data })
+        value.parse()
     }
 }
 
@@ -47,54 +228,398 @@ impl Display for Segment {
     }
 }
 
+impl FromStr for Segment {
+    type Err = ParseError;
+
+    /// Parse a segment as `aa:bb`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = parse_hex_groups(s, ':', 2)?;
+        match bytes.len() {
+            2 => Ok(Segment { data: bytes.try_into().unwrap() }),
+            len => Err(ParseError::WrongLength { found: len, expected: 2 }),
+        }
+    }
+}
+
 impl TryFrom<&str> for Segment {
-    type Error = ();
+    type Error = ParseError;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        if value.len() != 5 {
-            return Err(());
-        }
-        let mut data = [0; 2];
-        data[0] = u8::from_str_radix(&value[0..2], 16).map_err(|_| ())?;
-        data[1] = u8::from_str_radix(&value[3..5], 16).map_err(|_| ())?;
-        Ok(Segment { data })
+        value.parse()
     }
 }
 
 pub type FrameData = [u8; 4];
 
+/// VLAN that untagged frames belong to unless ports tell otherwise.
+pub const DEFAULT_VLAN: u16 = 1;
+
+/// IEEE 802.1Q tag, with VLAN identifier and priority code point.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct VlanTag {
+    pub vid: u16,
+    pub pcp: u8,
+}
+
+impl Display for VlanTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.vid, self.pcp)
+    }
+}
+
+impl FromStr for VlanTag {
+    type Err = ParseError;
+
+    /// Parse a tag as `vid/pcp` in decimal.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (vid, pcp) = s.split_once('/').ok_or(ParseError::MissingField("pcp"))?;
+        let vid = vid.parse::<u16>().ok().filter(|vid| *vid <= 0xfff).ok_or(ParseError::BadField("vid", 0))?;
+        let pcp = pcp.parse::<u8>().ok().filter(|pcp| *pcp <= 7)
+            .ok_or(ParseError::BadField("pcp", s.len() - pcp.len()))?;
+        Ok(VlanTag { vid, pcp })
+    }
+}
+
+impl TryFrom<&str> for VlanTag {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct Frame {
     pub src: Address,
     pub src_seg: Segment,
     pub dst: Address,
-    pub data: FrameData
+    pub data: FrameData,
+    /// Tag of the frame if any, absent in datasets from before VLANs.
+    #[serde(default)]
+    pub vlan: Option<VlanTag>,
 }
 
 impl Display for Frame {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {} {} {:02x?}", self.src, self.src_seg, self.dst, self.data)
+        write!(f, "{} {} {} ", self.src, self.src_seg, self.dst)?;
+        for b in self.data {
+            write!(f, "{b:02x}")?;
+        }
+        if let Some(tag) = self.vlan {
+            write!(f, " {}", tag)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Frame {
+    type Err = ParseError;
+
+    /// Parse a frame as written by `Display`, i.e. `src src_seg dst data [vid/pcp]` with the data in
+    /// hexadecimal digits.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = Fields::new(s);
+        let src = fields.parse("src")?;
+        let src_seg = fields.parse("src_seg")?;
+        let dst = fields.parse("dst")?;
+        let (data, pos) = fields.next("data")?;
+        let data = parse_hex(data, size_of::<FrameData>() * 2).map_err(|err| err.at(pos))?;
+        let vlan = match fields.optional() {
+            Some((tag, pos)) => Some(tag.parse::<VlanTag>().map_err(|err| err.at(pos))?),
+            None => None,
+        };
+        fields.end()?;
+        Ok(Frame { src, src_seg, dst, data: data.try_into().unwrap(), vlan })
     }
 }
 
 impl TryFrom<&str> for Frame {
-    type Error = ();
+    type Error = ParseError;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let mut seg = value.trim().split(' ');
-        let src = if let Some(val) = seg.next() { val } else { return Err(()) };
-        let src_seg = if let Some(val) = seg.next() { val } else { return Err(()) };
-        let dst = if let Some(val) = seg.next() { val } else { return Err(()) };
-        let data_s = if let Some(val) = seg.next() { val } else { return Err(()) };
-        let mut data = FrameData::default();
-        for i in 0..16 {
-            data[i] = u8::from_str_radix(&data_s[i * 2..i * 2 + 2], 16).map_err(|_| ())?;
-        }
-        Ok(Frame {
-            src: src.try_into()?,
-            src_seg: src_seg.try_into()?,
-            dst: dst.try_into()?,
-            data
-        })
-    }
-}
\ No newline at end of file
+        value.parse()
+    }
+}
+
+/// Migration of a station to another segment, taking effect from the frame of specified index.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Migration {
+    pub index: usize,
+    pub addr: Address,
+    pub seg: Segment,
+}
+
+impl Display for Migration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.index, self.addr, self.seg)
+    }
+}
+
+impl FromStr for Migration {
+    type Err = ParseError;
+
+    /// Parse a migration as written by `Display`, i.e. `index addr seg`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = Fields::new(s);
+        let (index, pos) = fields.next("index")?;
+        let index = index.parse().map_err(|_| ParseError::BadField("index", pos))?;
+        let addr = fields.parse("addr")?;
+        let seg = fields.parse("seg")?;
+        fields.end()?;
+        Ok(Migration { index, addr, seg })
+    }
+}
+
+/// Change of membership of a station in a multicast group, as told by an IGMP report from its
+/// segment, taking effect from the frame of specified index.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Membership {
+    pub index: usize,
+    pub addr: Address,
+    pub group: Address,
+    pub seg: Segment,
+    /// Whether the station joins the group, or leaves it.
+    pub join: bool,
+}
+
+impl Display for Membership {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let action = if self.join { "join" } else { "leave" };
+        write!(f, "{} {} {} {} {}", self.index, self.addr, self.group, self.seg, action)
+    }
+}
+
+impl FromStr for Membership {
+    type Err = ParseError;
+
+    /// Parse a membership as written by `Display`, i.e. `index addr group seg join|leave`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = Fields::new(s);
+        let (index, pos) = fields.next("index")?;
+        let index = index.parse().map_err(|_| ParseError::BadField("index", pos))?;
+        let addr = fields.parse("addr")?;
+        let group = fields.parse("group")?;
+        let seg = fields.parse("seg")?;
+        let join = match fields.next("action")? {
+            ("join", _) => true,
+            ("leave", _) => false,
+            (_, pos) => return Err(ParseError::BadField("action", pos)),
+        };
+        fields.end()?;
+        Ok(Membership { index, addr, group, seg, join })
+    }
+}
+
+/// Metadata of a generated dataset, sufficient to generate it again.
+///
+/// The traffic matrix and the mix of flows are inline TOML tables, as in the scenario of generation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    pub seed: u64,
+    pub valid_addr_cnt: usize,
+    pub invalid_addr_cnt: usize,
+    pub seg_cnt: usize,
+    pub valid_frame_cnt: usize,
+    pub invalid_frame_cnt: usize,
+    pub migration_cnt: usize,
+    #[serde(default)]
+    pub broadcast_frame_cnt: usize,
+    #[serde(default)]
+    pub multicast_frame_cnt: usize,
+    #[serde(default)]
+    pub group_cnt: usize,
+    #[serde(default)]
+    pub group_size: usize,
+    #[serde(default)]
+    pub membership_cnt: usize,
+    #[serde(default)]
+    pub traffic: String,
+    #[serde(default)]
+    pub intra_segment_ratio: Option<f64>,
+    #[serde(default)]
+    pub flows: String,
+    #[serde(default)]
+    pub frame_trace: bool,
+}
+
+impl Display for Metadata {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "valid_addr_cnt {}", self.valid_addr_cnt)?;
+        writeln!(f, "invalid_addr_cnt {}", self.invalid_addr_cnt)?;
+        writeln!(f, "seg_cnt {}", self.seg_cnt)?;
+        writeln!(f, "valid_frame_cnt {}", self.valid_frame_cnt)?;
+        writeln!(f, "invalid_frame_cnt {}", self.invalid_frame_cnt)?;
+        writeln!(f, "migration_cnt {}", self.migration_cnt)?;
+        writeln!(f, "broadcast_frame_cnt {}", self.broadcast_frame_cnt)?;
+        writeln!(f, "multicast_frame_cnt {}", self.multicast_frame_cnt)?;
+        writeln!(f, "group_cnt {}", self.group_cnt)?;
+        writeln!(f, "group_size {}", self.group_size)?;
+        writeln!(f, "membership_cnt {}", self.membership_cnt)?;
+        writeln!(f, "traffic {}", self.traffic)?;
+        if let Some(ratio) = self.intra_segment_ratio {
+            writeln!(f, "intra_segment_ratio {}", ratio)?;
+        }
+        writeln!(f, "flows {}", self.flows)?;
+        writeln!(f, "frame_trace {}", self.frame_trace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rounds of each property, over values drawn from a fixed seed.
+    const ROUNDS: usize = 1000;
+
+    fn address(rng: &mut fastrand::Rng) -> Address {
+        let mut data = [0; 6];
+        rng.fill(&mut data);
+        Address { data }
+    }
+
+    fn segment(rng: &mut fastrand::Rng) -> Segment {
+        Segment { data: [rng.u8(..), rng.u8(..)] }
+    }
+
+    fn vlan_tag(rng: &mut fastrand::Rng) -> VlanTag {
+        VlanTag { vid: rng.u16(..=0xfff), pcp: rng.u8(..=7) }
+    }
+
+    fn frame(rng: &mut fastrand::Rng) -> Frame {
+        let mut data = [0; 4];
+        rng.fill(&mut data);
+        let vlan = if rng.bool() { Some(vlan_tag(rng)) } else { None };
+        Frame { src: address(rng), src_seg: segment(rng), dst: address(rng), data, vlan }
+    }
+
+    #[test]
+    fn address_round_trip() {
+        let mut rng = fastrand::Rng::with_seed(19);
+        for _ in 0..ROUNDS {
+            let addr = address(&mut rng);
+            let [a1, a2, a3, a4, a5, a6] = addr.data;
+            assert_eq!(addr.to_string().parse(), Ok(addr));
+            assert_eq!(addr.to_string().replace(':', "-").parse(), Ok(addr));
+            assert_eq!(addr.to_string().to_uppercase().parse(), Ok(addr));
+            assert_eq!(format!("{a1:02x}{a2:02x}.{a3:02x}{a4:02x}.{a5:02x}{a6:02x}").parse(), Ok(addr));
+        }
+    }
+
+    #[test]
+    fn legacy_address() {
+        let mut rng = fastrand::Rng::with_seed(19);
+        for _ in 0..ROUNDS {
+            let mut data = [0; 4];
+            rng.fill(&mut data);
+            let [a, b, c, d] = data;
+            let addr = Address { data: [0x02, 0x00, a, b, c, d] };
+            assert_eq!(Address::from_legacy(data), addr);
+            assert!(addr.is_local() && addr.is_unicast());
+            assert_eq!(format!("{a:02x}:{b:02x}:{c:02x}:{d:02x}").parse(), Ok(addr));
+        }
+    }
+
+    #[test]
+    fn legacy_address_serde() {
+        #[derive(Serialize)]
+        #[serde(rename = "Address")]
+        struct Legacy {
+            data: [u8; 4],
+        }
+
+        let legacy = Legacy { data: [0xaa, 0xbb, 0xcc, 0xdd] };
+        let addr: Address = "02:00:aa:bb:cc:dd".parse().unwrap();
+        let bytes = rmp_serde::to_vec(&legacy).unwrap();
+        assert_eq!(rmp_serde::from_slice::<Address>(&bytes).unwrap(), addr);
+        let bytes = serde_pickle::to_vec(&legacy, Default::default()).unwrap();
+        assert_eq!(serde_pickle::from_slice::<Address>(&bytes, Default::default()).unwrap(), addr);
+
+        let bytes = rmp_serde::to_vec(&addr).unwrap();
+        assert_eq!(rmp_serde::from_slice::<Address>(&bytes).unwrap(), addr);
+        let bytes = rmp_serde::to_vec(&Legacy { data: [0; 4] }).unwrap();
+        assert_eq!(rmp_serde::from_slice::<Address>(&bytes).unwrap(), Address::from_legacy([0; 4]));
+        assert!(rmp_serde::from_slice::<Address>(&rmp_serde::to_vec(&([0u8; 5],)).unwrap()).is_err());
+    }
+
+    #[test]
+    fn segment_round_trip() {
+        let mut rng = fastrand::Rng::with_seed(19);
+        for _ in 0..ROUNDS {
+            let seg = segment(&mut rng);
+            assert_eq!(seg.to_string().parse(), Ok(seg));
+        }
+    }
+
+    #[test]
+    fn vlan_tag_round_trip() {
+        let mut rng = fastrand::Rng::with_seed(19);
+        for _ in 0..ROUNDS {
+            let tag = vlan_tag(&mut rng);
+            assert_eq!(tag.to_string().parse(), Ok(tag));
+        }
+    }
+
+    #[test]
+    fn frame_round_trip() {
+        let mut rng = fastrand::Rng::with_seed(19);
+        for _ in 0..ROUNDS {
+            let frame = frame(&mut rng);
+            assert_eq!(frame.to_string().parse(), Ok(frame.clone()));
+            assert_eq!(Frame::try_from(frame.to_string().as_str()), Ok(frame));
+        }
+    }
+
+    #[test]
+    fn address_errors() {
+        assert_eq!("02:00:0g:00:00:01".parse::<Address>(), Err(ParseError::BadHexDigit(7)));
+        assert_eq!("0200.00z0.0001".parse::<Address>(), Err(ParseError::BadHexDigit(7)));
+        assert_eq!("02-00-00-00-00-0/".parse::<Address>(), Err(ParseError::BadHexDigit(16)));
+        assert_eq!("02:00:00:00:00".parse::<Address>(), Err(ParseError::WrongLength { found: 5, expected: 6 }));
+        assert_eq!("02-00-00-00".parse::<Address>(), Err(ParseError::WrongLength { found: 4, expected: 6 }));
+        assert_eq!("02:00:000:00:00:01".parse::<Address>(), Err(ParseError::WrongLength { found: 3, expected: 2 }));
+        assert_eq!("0200.0000".parse::<Address>(), Err(ParseError::WrongLength { found: 4, expected: 6 }));
+        assert_eq!("".parse::<Address>(), Err(ParseError::WrongLength { found: 0, expected: 2 }));
+    }
+
+    #[test]
+    fn segment_and_tag_errors() {
+        assert_eq!("00:x1".parse::<Segment>(), Err(ParseError::BadHexDigit(3)));
+        assert_eq!("00:01:02".parse::<Segment>(), Err(ParseError::WrongLength { found: 3, expected: 2 }));
+        assert_eq!("10".parse::<VlanTag>(), Err(ParseError::MissingField("pcp")));
+        assert_eq!("4096/0".parse::<VlanTag>(), Err(ParseError::BadField("vid", 0)));
+        assert_eq!("10/8".parse::<VlanTag>(), Err(ParseError::BadField("pcp", 3)));
+    }
+
+    #[test]
+    fn frame_errors() {
+        let line = "02:00:00:00:00:01 00:01 02:00:00:00:00:02 01020304 10/3";
+        assert!(line.parse::<Frame>().is_ok());
+        let at = |field: &str| line.find(field).unwrap();
+
+        let bad = line.replace("00:01 ", "00:0x ");
+        assert_eq!(bad.parse::<Frame>(), Err(ParseError::BadHexDigit(at("00:01 ") + 4)));
+        let bad = line.replace(":02 ", ":g2 ");
+        assert_eq!(bad.parse::<Frame>(), Err(ParseError::BadHexDigit(at(":02 ") + 1)));
+        let bad = line.replace("01020304", "0102q304");
+        assert_eq!(bad.parse::<Frame>(), Err(ParseError::BadHexDigit(at("01020304") + 4)));
+        let bad = line.replace("01020304", "010203");
+        assert_eq!(bad.parse::<Frame>(), Err(ParseError::WrongLength { found: 6, expected: 8 }));
+        let bad = line.replace("10/3", "10/9");
+        assert_eq!(bad.parse::<Frame>(), Err(ParseError::BadField("pcp", at("10/3") + 3)));
+        let bad = format!("{} extra", line);
+        assert_eq!(bad.parse::<Frame>(), Err(ParseError::TrailingGarbage(line.len())));
+
+        assert_eq!(line[..at("02:00:00:00:00:02")].parse::<Frame>(), Err(ParseError::MissingField("dst")));
+        assert_eq!(line[..at("01020304")].parse::<Frame>(), Err(ParseError::MissingField("data")));
+    }
+
+    #[test]
+    fn errors_display() {
+        assert_eq!(ParseError::WrongLength { found: 5, expected: 6 }.to_string(), "5 found where 6 expected");
+        assert_eq!(ParseError::BadHexDigit(7).to_string(), "not a hexadecimal digit at 7");
+        assert_eq!(ParseError::MissingField("dst").to_string(), "missing dst");
+        assert_eq!(ParseError::BadField("pcp", 3).to_string(), "invalid pcp at 3");
+        assert_eq!(ParseError::TrailingGarbage(9).to_string(), "trailing characters from 9");
+    }
+}
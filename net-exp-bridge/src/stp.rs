@@ -0,0 +1,896 @@
+use std::fmt::{Display, Formatter};
+use serde::{Serialize, Deserialize};
+use crate::Address;
+
+/// Increase of message age of a BPDU per bridge relaying it, in milliseconds.
+pub const MESSAGE_AGE_INCREMENT: u64 = 1000;
+
+/// Identifier of a bridge, lower ones more likely to become the root.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct BridgeId {
+    pub priority: u16,
+    pub addr: Address,
+}
+
+impl Display for BridgeId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.priority, self.addr)
+    }
+}
+
+/// Identifier of a port, with its priority in the high byte and its number in the low byte.
+pub type PortId = u16;
+
+/// Priority vector of spanning tree information, better when smaller.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct PriorityVector {
+    pub root: BridgeId,
+    pub cost: u32,
+    pub bridge: BridgeId,
+    pub port: PortId,
+}
+
+/// Timers of the protocol in milliseconds, decided by the root for the whole tree.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Timers {
+    pub hello: u64,
+    pub max_age: u64,
+    pub forward_delay: u64,
+}
+
+impl Default for Timers {
+    fn default() -> Self {
+        Timers { hello: 2000, max_age: 20_000, forward_delay: 15_000 }
+    }
+}
+
+/// Kind of bridge protocol data unit.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum BpduKind {
+    /// Configuration, carrying spanning tree information.
+    Config,
+    /// Topology change notification, sent towards the root.
+    Tcn,
+    /// Rapid spanning tree, carrying the role of the sending port and handshake flags.
+    Rst,
+}
+
+/// Bridge protocol data unit exchanged between bridges on a segment.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Bpdu {
+    pub kind: BpduKind,
+    pub vector: PriorityVector,
+    /// Time since the root sent the information.
+    pub message_age: u64,
+    pub timers: Timers,
+    /// Topology change, i.e. bridges should age out entries faster.
+    pub tc: bool,
+    /// Topology change notification acknowledged.
+    pub tca: bool,
+    /// Role of the sending port (rapid only).
+    pub role: PortRole,
+    /// Designated port asks to forward at once (rapid only).
+    pub proposal: bool,
+    /// Port agrees to a proposal, having put its other ports in sync (rapid only).
+    pub agreement: bool,
+}
+
+/// Role of a port in the spanning tree.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum PortRole {
+    /// Link of the port is down.
+    Disabled,
+    /// Port towards the root, the best of the bridge.
+    Root,
+    /// Port forwarding for the segment on behalf of the tree.
+    Designated,
+    /// Port left out of the tree to break a loop.
+    Blocked,
+    /// Port left out of the tree, ready to replace the root port (rapid only).
+    Alternate,
+    /// Port left out of the tree, ready to replace another port of the bridge on the same segment
+    /// (rapid only).
+    Backup,
+}
+
+/// State of a port, deciding whether it learns and forwards frames.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum PortState {
+    Disabled,
+    Blocking,
+    Listening,
+    Learning,
+    Forwarding,
+    /// Neither learning nor forwarding, in place of disabled, blocking and listening (rapid only).
+    Discarding,
+}
+
+/// Output of the protocol for the bridge to act on.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StpAction {
+    /// Send a BPDU out of a port.
+    Send(usize, Bpdu),
+    /// Role or state of a port changed.
+    Changed(usize, PortRole, PortState),
+    /// Forget addresses learned on a port, the topology having changed (rapid only).
+    Flush(usize),
+}
+
+/// Information received on a port, with the time it arrived.
+#[derive(Debug, Copy, Clone)]
+struct Info {
+    vector: PriorityVector,
+    message_age: u64,
+    received: u64,
+}
+
+struct Port {
+    id: PortId,
+    cost: u32,
+    enabled: bool,
+    /// Whether no bridge is expected on the segment, so the port may forward at once.
+    edge: bool,
+    /// Whether no bridge has been heard on the segment while the port is an edge one.
+    oper_edge: bool,
+    /// Whether a bridge of classic spanning tree was heard, so the port falls back to it.
+    legacy: bool,
+    role: PortRole,
+    state: PortState,
+    /// Time the state last changed, for the forward delay.
+    since: u64,
+    info: Option<Info>,
+}
+
+/// Spanning Tree Protocol (IEEE 802.1D) of a bridge, or its rapid variant (IEEE 802.1w).
+///
+/// Times are milliseconds from any fixed origin. The bridge feeds received BPDUs, link changes and
+/// a periodic tick, and acts on the emitted actions.
+///
+/// In rapid mode, designated ports forward once the bridge on the other side agrees to their
+/// proposal, root ports forward at once, alternate ports take over from failed root ports and
+/// edge ports forward from the start. Ports hearing classic BPDUs fall back to timers, and
+/// designated ports never answered do after twice the forward delay.
+pub struct Stp {
+    id: BridgeId,
+    rapid: bool,
+    /// Timers of this bridge, used when it is the root.
+    config: Timers,
+    /// Timers in use, learned from the root.
+    timers: Timers,
+    ports: Vec<Port>,
+    root: BridgeId,
+    root_cost: u32,
+    root_port: Option<usize>,
+    last_hello: u64,
+    /// End of topology change announced by this bridge as the root.
+    tc_until: u64,
+    /// Topology change flag last received from the root.
+    tc: bool,
+    /// Time a notification was last sent and not yet acknowledged.
+    tcn_sent: Option<u64>,
+    /// End of topology change flagged in rapid BPDUs.
+    tc_while: u64,
+}
+
+impl Stp {
+    /// Create the protocol for a bridge with path costs of its ports.
+    pub fn new(id: BridgeId, costs: &[u32], timers: Timers) -> Self {
+        let ports = costs.iter()
+            .enumerate()
+            .map(|(i, cost)| Port {
+                id: 0x8000 | i as u16,
+                cost: *cost,
+                enabled: true,
+                edge: false,
+                oper_edge: false,
+                legacy: false,
+                role: PortRole::Designated,
+                state: PortState::Blocking,
+                since: 0,
+                info: None,
+            })
+            .collect();
+        Stp {
+            id,
+            rapid: false,
+            config: timers,
+            timers,
+            ports,
+            root: id,
+            root_cost: 0,
+            root_port: None,
+            last_hello: 0,
+            tc_until: 0,
+            tc: false,
+            tcn_sent: None,
+            tc_while: 0,
+        }
+    }
+
+    /// Run the rapid variant, with ports expecting no bridge on their segments as edge ports.
+    pub fn with_rapid(mut self, edges: &[bool]) -> Self {
+        self.rapid = true;
+        for (port, edge) in self.ports.iter_mut().zip(edges) {
+            port.edge = *edge;
+            port.oper_edge = *edge;
+            port.state = PortState::Discarding;
+        }
+        self
+    }
+
+    pub fn is_rapid(&self) -> bool {
+        self.rapid
+    }
+
+    /// Start as the root of its own, sending the first BPDUs.
+    pub fn start(&mut self, now: u64, out: &mut impl FnMut(StpAction)) {
+        self.update(now, out);
+        self.hello(now, out);
+    }
+
+    pub fn id(&self) -> BridgeId {
+        self.id
+    }
+
+    pub fn root(&self) -> BridgeId {
+        self.root
+    }
+
+    pub fn root_cost(&self) -> u32 {
+        self.root_cost
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.root == self.id
+    }
+
+    pub fn timers(&self) -> Timers {
+        self.timers
+    }
+
+    pub fn role(&self, port: usize) -> PortRole {
+        self.ports[port].role
+    }
+
+    pub fn state(&self, port: usize) -> PortState {
+        self.ports[port].state
+    }
+
+    /// Whether frames received on a port are learned.
+    pub fn learning(&self, port: usize) -> bool {
+        matches!(self.ports[port].state, PortState::Learning | PortState::Forwarding)
+    }
+
+    /// Whether frames are forwarded from and to a port.
+    pub fn forwarding(&self, port: usize) -> bool {
+        self.ports[port].state == PortState::Forwarding
+    }
+
+    /// Whether the tree is changing, so entries should age out after the forward delay.
+    pub fn topology_change(&self, now: u64) -> bool {
+        if self.rapid {
+            now < self.tc_while
+        } else if self.is_root() {
+            now < self.tc_until
+        } else {
+            self.tc
+        }
+    }
+
+    /// Whether a port speaks the rapid variant.
+    fn is_rapid_port(&self, port: usize) -> bool {
+        self.rapid && !self.ports[port].legacy
+    }
+
+    /// State of ports left out of the tree.
+    fn discarding(&self) -> PortState {
+        if self.rapid { PortState::Discarding } else { PortState::Blocking }
+    }
+
+    /// Our priority vector as the designated bridge of a port.
+    fn designated_vector(&self, port: usize) -> PriorityVector {
+        PriorityVector { root: self.root, cost: self.root_cost, bridge: self.id, port: self.ports[port].id }
+    }
+
+    /// Configuration to send out of a port, as a rapid BPDU if the port speaks it.
+    fn config_bpdu(&self, port: usize, now: u64) -> Bpdu {
+        let message_age = match self.root_port {
+            Some(p) => self.ports[p].info.map(|i| i.message_age).unwrap_or(0) + MESSAGE_AGE_INCREMENT,
+            None => 0,
+        };
+        let p = &self.ports[port];
+        let rapid = self.is_rapid_port(port);
+        Bpdu {
+            kind: if rapid { BpduKind::Rst } else { BpduKind::Config },
+            vector: self.designated_vector(port),
+            message_age,
+            timers: self.timers,
+            tc: self.topology_change(now),
+            tca: false,
+            role: if rapid { p.role } else { PortRole::Designated },
+            proposal: rapid && p.role == PortRole::Designated && p.state != PortState::Forwarding && !p.oper_edge,
+            agreement: false,
+        }
+    }
+
+    /// Send configuration out of all designated ports.
+    fn hello(&mut self, now: u64, out: &mut impl FnMut(StpAction)) {
+        for p in 0..self.ports.len() {
+            if self.ports[p].enabled && self.ports[p].role == PortRole::Designated {
+                out(StpAction::Send(p, self.config_bpdu(p, now)));
+            }
+        }
+        self.last_hello = now;
+    }
+
+    /// Put designated ports in sync with new information of the root port by making them discard
+    /// until bridges below agree, then agree to the proposal on the root port.
+    fn sync(&mut self, now: u64, out: &mut impl FnMut(StpAction)) {
+        let Some(root_port) = self.root_port else {
+            return;
+        };
+        for i in 0..self.ports.len() {
+            let p = &self.ports[i];
+            if i != root_port && p.role == PortRole::Designated && !p.oper_edge {
+                self.set_state(i, PortState::Discarding, now, out);
+            }
+        }
+        self.hello(now, out);
+        let mut agreement = self.config_bpdu(root_port, now);
+        agreement.agreement = true;
+        out(StpAction::Send(root_port, agreement));
+    }
+
+    /// Handle a change of topology heard on a port in rapid mode, forgetting addresses learned on
+    /// other ports and passing the change on.
+    fn rapid_change(&mut self, port: usize, now: u64, out: &mut impl FnMut(StpAction)) {
+        for i in 0..self.ports.len() {
+            if i != port && !self.ports[i].oper_edge {
+                out(StpAction::Flush(i));
+            }
+        }
+        if now < self.tc_while {
+            return;
+        }
+        self.tc_while = now + 2 * self.timers.hello;
+        for i in 0..self.ports.len() {
+            let p = &self.ports[i];
+            if i == port || !p.enabled || !matches!(p.role, PortRole::Root | PortRole::Designated) {
+                continue;
+            }
+            if p.legacy && p.role == PortRole::Root {
+                self.tcn_sent = Some(now);
+                out(StpAction::Send(i, self.tcn_bpdu()));
+            } else {
+                out(StpAction::Send(i, self.config_bpdu(i, now)));
+            }
+        }
+    }
+
+    fn set_state(&mut self, port: usize, state: PortState, now: u64, out: &mut impl FnMut(StpAction)) {
+        let p = &mut self.ports[port];
+        if p.state == state {
+            return;
+        }
+        let was_forwarding = p.state == PortState::Forwarding;
+        let edge = p.oper_edge;
+        p.state = state;
+        p.since = now;
+        out(StpAction::Changed(port, p.role, state));
+        if self.rapid {
+            // only ports starting to forward change the topology, edge ones never
+            if state == PortState::Forwarding && !edge {
+                self.rapid_change(port, now, out);
+            }
+        } else if state == PortState::Forwarding || was_forwarding {
+            self.detect_change(now, out);
+        }
+    }
+
+    /// Announce a change of the active topology, directly as the root or by notifying it.
+    fn detect_change(&mut self, now: u64, out: &mut impl FnMut(StpAction)) {
+        if self.is_root() {
+            self.tc_until = now + self.timers.max_age + self.timers.forward_delay;
+        } else if let Some(p) = self.root_port {
+            self.tcn_sent = Some(now);
+            out(StpAction::Send(p, self.tcn_bpdu()));
+        }
+    }
+
+    fn tcn_bpdu(&self) -> Bpdu {
+        Bpdu {
+            kind: BpduKind::Tcn,
+            vector: self.designated_vector(self.root_port.unwrap_or(0)),
+            message_age: 0,
+            timers: self.timers,
+            tc: false,
+            tca: false,
+            role: PortRole::Root,
+            proposal: false,
+            agreement: false,
+        }
+    }
+
+    /// Choose the root port and roles of ports from the information received, then move the states
+    /// of ports following the new roles.
+    fn update(&mut self, now: u64, out: &mut impl FnMut(StpAction)) {
+        let was_root = self.is_root();
+        let best = self.ports.iter()
+            .enumerate()
+            .filter(|(_, p)| p.enabled)
+            .filter_map(|(i, p)| p.info.map(|info| {
+                let vector = PriorityVector { cost: info.vector.cost + p.cost, ..info.vector };
+                (vector, p.id, i)
+            }))
+            .filter(|(vector, _, _)| vector.root < self.id)
+            .min();
+        match best {
+            Some((vector, _, i)) => {
+                self.root = vector.root;
+                self.root_cost = vector.cost;
+                self.root_port = Some(i);
+            }
+            None => {
+                self.root = self.id;
+                self.root_cost = 0;
+                self.root_port = None;
+                self.timers = self.config;
+                self.tc = false;
+            }
+        }
+        for i in 0..self.ports.len() {
+            let role = if !self.ports[i].enabled {
+                PortRole::Disabled
+            } else if Some(i) == self.root_port {
+                PortRole::Root
+            } else {
+                match self.ports[i].info {
+                    Some(info) if info.vector <= self.designated_vector(i) => {
+                        if !self.rapid {
+                            PortRole::Blocked
+                        } else if info.vector.bridge == self.id {
+                            PortRole::Backup
+                        } else {
+                            PortRole::Alternate
+                        }
+                    }
+                    _ => PortRole::Designated,
+                }
+            };
+            let state = self.ports[i].state;
+            if self.ports[i].role != role {
+                self.ports[i].role = role;
+                self.ports[i].since = now;
+                out(StpAction::Changed(i, role, state));
+            }
+            let discarding = self.discarding();
+            match role {
+                PortRole::Disabled => {
+                    let disabled = if self.rapid { PortState::Discarding } else { PortState::Disabled };
+                    self.set_state(i, disabled, now, out);
+                }
+                PortRole::Blocked | PortRole::Alternate | PortRole::Backup => {
+                    self.set_state(i, discarding, now, out);
+                }
+                PortRole::Root if self.rapid => self.set_state(i, PortState::Forwarding, now, out),
+                PortRole::Designated if self.rapid && self.ports[i].oper_edge => {
+                    self.set_state(i, PortState::Forwarding, now, out);
+                }
+                PortRole::Root | PortRole::Designated => {
+                    if matches!(state, PortState::Disabled | PortState::Blocking) {
+                        self.set_state(i, PortState::Listening, now, out);
+                    }
+                }
+            }
+        }
+        if was_root != self.is_root() && self.is_root() {
+            // lost the way to the root, take over until told otherwise
+            self.hello(now, out);
+        }
+    }
+
+    /// Handle a BPDU received on a port.
+    pub fn receive(&mut self, port: usize, bpdu: Bpdu, now: u64, out: &mut impl FnMut(StpAction)) {
+        if !self.ports[port].enabled {
+            return;
+        }
+        if self.rapid {
+            self.ports[port].oper_edge = false;
+            if bpdu.kind != BpduKind::Rst {
+                self.ports[port].legacy = true;
+            }
+        }
+        match bpdu.kind {
+            BpduKind::Rst if !self.rapid && bpdu.role != PortRole::Designated => {
+                // only information of designated ports makes sense to classic bridges
+            }
+            BpduKind::Rst if self.rapid && bpdu.role != PortRole::Designated => {
+                let p = &self.ports[port];
+                if bpdu.agreement && p.role == PortRole::Designated && bpdu.vector.root == self.root {
+                    self.set_state(port, PortState::Forwarding, now, out);
+                }
+                if bpdu.tc && self.ports[port].role == PortRole::Designated {
+                    self.rapid_change(port, now, out);
+                }
+            }
+            BpduKind::Config | BpduKind::Rst => {
+                if bpdu.message_age >= bpdu.timers.max_age {
+                    return;
+                }
+                let p = &self.ports[port];
+                // information of the designated bridge of the segment, us or the one we heard
+                let current = match p.info {
+                    Some(info) if p.role != PortRole::Designated => info.vector,
+                    _ => self.designated_vector(port),
+                };
+                let same_sender = p.info.is_some_and(|info| {
+                    (info.vector.bridge, info.vector.port) == (bpdu.vector.bridge, bpdu.vector.port)
+                });
+                if bpdu.vector < current || same_sender {
+                    self.ports[port].info = Some(Info {
+                        vector: bpdu.vector,
+                        message_age: bpdu.message_age,
+                        received: now,
+                    });
+                    self.update(now, out);
+                    if Some(port) == self.root_port {
+                        self.timers = bpdu.timers;
+                        if bpdu.tca {
+                            self.tcn_sent = None;
+                        }
+                        if !self.rapid {
+                            // relay the information of the root down the tree
+                            self.tc = bpdu.tc;
+                            self.hello(now, out);
+                        } else {
+                            if bpdu.tc {
+                                self.rapid_change(port, now, out);
+                            }
+                            if bpdu.proposal && self.is_rapid_port(port) {
+                                self.sync(now, out);
+                            }
+                        }
+                    } else if self.rapid && matches!(self.ports[port].role, PortRole::Alternate | PortRole::Backup) {
+                        // discarding already, so nothing to sync before agreeing
+                        if bpdu.proposal && self.is_rapid_port(port) {
+                            let mut agreement = self.config_bpdu(port, now);
+                            agreement.agreement = true;
+                            out(StpAction::Send(port, agreement));
+                        }
+                    } else if self.ports[port].role == PortRole::Designated {
+                        // the sender got worse than us
+                        out(StpAction::Send(port, self.config_bpdu(port, now)));
+                    }
+                } else if self.ports[port].role == PortRole::Designated {
+                    // inferior information, tell the sender about the better one
+                    out(StpAction::Send(port, self.config_bpdu(port, now)));
+                }
+            }
+            BpduKind::Tcn => {
+                if self.ports[port].role != PortRole::Designated {
+                    return;
+                }
+                let mut ack = self.config_bpdu(port, now);
+                ack.tca = true;
+                out(StpAction::Send(port, ack));
+                if self.rapid {
+                    self.rapid_change(port, now, out);
+                } else {
+                    self.detect_change(now, out);
+                }
+            }
+        }
+    }
+
+    /// Advance timers: age out information, send hellos as the root and move ports through
+    /// listening and learning to forwarding.
+    pub fn tick(&mut self, now: u64, out: &mut impl FnMut(StpAction)) {
+        let max_age = self.timers.max_age;
+        // rapid bridges all send hellos, so information is lost after three missed ones
+        let lost_after = 3 * self.timers.hello;
+        let mut expired = false;
+        for i in 0..self.ports.len() {
+            let rapid = self.is_rapid_port(i);
+            let p = &mut self.ports[i];
+            if let Some(info) = p.info {
+                let age = now - info.received;
+                if info.message_age + age >= max_age || (rapid && age >= lost_after) {
+                    p.info = None;
+                    expired = true;
+                }
+            }
+        }
+        if expired {
+            self.update(now, out);
+        }
+        if (self.is_root() || self.rapid) && now - self.last_hello >= self.timers.hello {
+            self.hello(now, out);
+        }
+        if let (Some(sent), Some(p)) = (self.tcn_sent, self.root_port) {
+            if now - sent >= self.timers.hello {
+                self.tcn_sent = Some(now);
+                out(StpAction::Send(p, self.tcn_bpdu()));
+            }
+        }
+        for i in 0..self.ports.len() {
+            if now - self.ports[i].since < self.timers.forward_delay {
+                continue;
+            }
+            let active = matches!(self.ports[i].role, PortRole::Root | PortRole::Designated);
+            match self.ports[i].state {
+                PortState::Listening => self.set_state(i, PortState::Learning, now, out),
+                PortState::Discarding if active => self.set_state(i, PortState::Learning, now, out),
+                PortState::Learning => self.set_state(i, PortState::Forwarding, now, out),
+                _ => {}
+            }
+        }
+    }
+
+    /// Link of a port went down.
+    pub fn link_down(&mut self, port: usize, now: u64, out: &mut impl FnMut(StpAction)) {
+        self.ports[port].enabled = false;
+        self.ports[port].info = None;
+        self.update(now, out);
+    }
+
+    /// Link of a port came up.
+    pub fn link_up(&mut self, port: usize, now: u64, out: &mut impl FnMut(StpAction)) {
+        let p = &mut self.ports[port];
+        p.enabled = true;
+        p.oper_edge = p.edge;
+        p.legacy = false;
+        self.update(now, out);
+        if self.ports[port].role == PortRole::Designated {
+            out(StpAction::Send(port, self.config_bpdu(port, now)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Interval between two ticks of the bridges.
+    const STEP: u64 = 100;
+
+    fn id(n: u8) -> BridgeId {
+        BridgeId { priority: 0x8000, addr: Address { data: [0x02, 0, 0, 0, 0, n] } }
+    }
+
+    /// Port of a bridge, by their indices.
+    type End = (usize, usize);
+
+    /// Bridges joined by point-to-point links between their ports, BPDUs crossing at once.
+    struct Net {
+        bridges: Vec<Stp>,
+        /// Links as pairs of (bridge, port), with whether they carry BPDUs.
+        links: Vec<(End, End, bool)>,
+        /// BPDUs sent so far, by bridge and port.
+        sent: Vec<(usize, usize, Bpdu)>,
+    }
+
+    impl Net {
+        fn new(bridges: Vec<Stp>, links: &[(End, End)]) -> Self {
+            let links = links.iter().map(|(a, b)| (*a, *b, true)).collect();
+            Net { bridges, links, sent: Vec::new() }
+        }
+
+        /// Bridge and port on the other end of a live link from a port.
+        fn peer(&self, end: End) -> Option<End> {
+            self.links.iter()
+                .filter(|(_, _, up)| *up)
+                .find_map(|(a, b, _)| if *a == end { Some(*b) } else if *b == end { Some(*a) } else { None })
+        }
+
+        /// Stop a link from carrying BPDUs without telling the bridges, as when it fails silently.
+        fn cut(&mut self, link: usize) {
+            self.links[link].2 = false;
+        }
+
+        /// Run an operation on a bridge, then deliver the BPDUs sent until none is left.
+        fn on(&mut self, bridge: usize, now: u64, f: impl FnOnce(&mut Stp, &mut dyn FnMut(StpAction))) {
+            let mut queue = Vec::new();
+            f(&mut self.bridges[bridge], &mut |a| queue.push((bridge, a)));
+            let mut next = 0;
+            while next < queue.len() {
+                assert!(next < 10_000, "BPDUs keep coming");
+                if let (from, StpAction::Send(port, bpdu)) = queue[next] {
+                    self.sent.push((from, port, bpdu));
+                    if let Some((to, to_port)) = self.peer((from, port)) {
+                        self.bridges[to].receive(to_port, bpdu, now, &mut |a| queue.push((to, a)));
+                    }
+                }
+                next += 1;
+            }
+        }
+
+        fn start(&mut self, now: u64) {
+            for b in 0..self.bridges.len() {
+                self.on(b, now, |stp, out| stp.start(now, &mut |a| out(a)));
+            }
+        }
+
+        /// Tick all bridges from one time to another, both excluded.
+        fn run(&mut self, from: u64, to: u64) {
+            let mut now = from + STEP;
+            while now < to {
+                for b in 0..self.bridges.len() {
+                    self.on(b, now, |stp, out| stp.tick(now, &mut |a| out(a)));
+                }
+                now += STEP;
+            }
+        }
+
+        /// Ports of all bridges in a state.
+        fn count(&self, state: PortState) -> usize {
+            self.bridges.iter()
+                .map(|stp| (0..stp.ports.len()).filter(|p| stp.state(*p) == state).count())
+                .sum()
+        }
+    }
+
+    /// Time for a classic tree to settle from the start.
+    fn settled(timers: Timers) -> u64 {
+        2 * timers.forward_delay + 5 * timers.hello
+    }
+
+    #[test]
+    fn lowest_id_becomes_root() {
+        let timers = Timers::default();
+        // ring of four bridges, the lowest id neither first nor last to start
+        let bridges = [4, 2, 3, 9].map(|n| Stp::new(id(n), &[19, 19], timers)).into();
+        let mut net = Net::new(bridges, &[((0, 1), (1, 0)), ((1, 1), (2, 0)), ((2, 1), (3, 0)), ((3, 1), (0, 0))]);
+        net.start(0);
+        net.run(0, settled(timers));
+        for stp in &net.bridges {
+            assert_eq!(stp.root(), id(2));
+        }
+        assert!(net.bridges[1].is_root());
+        assert_eq!(net.bridges.iter().map(|stp| stp.root_cost()).collect::<Vec<_>>(), [19, 0, 19, 38]);
+        // one port of the ring blocked, the others forwarding
+        assert_eq!(net.count(PortState::Blocking), 1);
+        assert_eq!(net.count(PortState::Forwarding), 7);
+    }
+
+    #[test]
+    fn loop_of_two_bridges_blocks_one_port() {
+        let timers = Timers::default();
+        let bridges = vec![Stp::new(id(2), &[19, 19], timers), Stp::new(id(1), &[19, 19], timers)];
+        let mut net = Net::new(bridges, &[((0, 0), (1, 0)), ((0, 1), (1, 1))]);
+        net.start(0);
+        net.run(0, settled(timers));
+        let (root, other) = (&net.bridges[1], &net.bridges[0]);
+        assert!(root.is_root());
+        for p in 0..2 {
+            assert_eq!((root.role(p), root.state(p)), (PortRole::Designated, PortState::Forwarding));
+        }
+        // the port of lower id wins between equal paths
+        assert_eq!((other.role(0), other.state(0)), (PortRole::Root, PortState::Forwarding));
+        assert_eq!((other.role(1), other.state(1)), (PortRole::Blocked, PortState::Blocking));
+        assert_eq!(net.count(PortState::Blocking), 1);
+    }
+
+    #[test]
+    fn ports_forward_after_twice_forward_delay() {
+        let timers = Timers::default();
+        let mut net = Net::new(vec![Stp::new(id(1), &[19], timers)], &[]);
+        net.start(0);
+        assert_eq!(net.bridges[0].state(0), PortState::Listening);
+        net.run(0, timers.forward_delay + STEP);
+        assert_eq!(net.bridges[0].state(0), PortState::Learning);
+        net.run(timers.forward_delay, 2 * timers.forward_delay);
+        assert!(!net.bridges[0].forwarding(0));
+        net.run(2 * timers.forward_delay - STEP, 2 * timers.forward_delay + STEP);
+        assert!(net.bridges[0].forwarding(0));
+    }
+
+    #[test]
+    fn blocked_port_takes_over_lost_root_port() {
+        let timers = Timers::default();
+        // triangle under root 1, bridge 3 blocking towards bridge 2
+        let bridges = [1, 2, 3].map(|n| Stp::new(id(n), &[19, 19], timers)).into();
+        let mut net = Net::new(bridges, &[((0, 0), (1, 0)), ((0, 1), (2, 0)), ((1, 1), (2, 1))]);
+        net.start(0);
+        let cut = settled(timers);
+        net.run(0, cut);
+        let stp = &net.bridges[2];
+        assert_eq!((stp.role(0), stp.role(1)), (PortRole::Root, PortRole::Blocked));
+        assert_eq!(stp.root_cost(), 19);
+
+        // the link to the root falls silent, its information aging out within a hello
+        net.cut(1);
+        let over = cut + timers.max_age + 2 * timers.forward_delay;
+        net.run(cut - STEP, over - timers.hello - STEP);
+        assert!(!net.bridges[2].forwarding(1));
+        net.run(over - timers.hello - 2 * STEP, over + STEP);
+        let stp = &net.bridges[2];
+        assert_eq!((stp.role(1), stp.state(1)), (PortRole::Root, PortState::Forwarding));
+        assert_eq!(stp.root(), id(1));
+        assert_eq!(stp.root_cost(), 38);
+    }
+
+    fn rapid(n: u8, ports: usize) -> Stp {
+        Stp::new(id(n), &vec![19; ports], Timers::default()).with_rapid(&vec![false; ports])
+    }
+
+    #[test]
+    fn proposal_and_agreement_forward_at_once() {
+        let mut net = Net::new(vec![rapid(1, 1), rapid(2, 1)], &[((0, 0), (1, 0))]);
+        net.start(0);
+        // no tick needed: the designated port proposes, the root port agrees
+        assert!(net.sent.iter().any(|(b, _, bpdu)| *b == 0 && bpdu.kind == BpduKind::Rst && bpdu.proposal));
+        assert!(net.sent.iter().any(|(b, _, bpdu)| *b == 1 && bpdu.role == PortRole::Root && bpdu.agreement));
+        assert_eq!((net.bridges[0].role(0), net.bridges[0].state(0)), (PortRole::Designated, PortState::Forwarding));
+        assert_eq!((net.bridges[1].role(0), net.bridges[1].state(0)), (PortRole::Root, PortState::Forwarding));
+        assert_eq!(net.bridges[1].root(), id(1));
+    }
+
+    #[test]
+    fn unanswered_proposal_waits_twice_forward_delay() {
+        let fd = Timers::default().forward_delay;
+        let mut net = Net::new(vec![rapid(1, 1)], &[]);
+        net.start(0);
+        assert_eq!(net.bridges[0].state(0), PortState::Discarding);
+        net.run(0, fd + STEP);
+        assert_eq!(net.bridges[0].state(0), PortState::Learning);
+        net.run(fd, 2 * fd + STEP);
+        assert!(net.bridges[0].forwarding(0));
+    }
+
+    #[test]
+    fn edge_port_forwards_from_start() {
+        let stp = Stp::new(id(1), &[19, 19], Timers::default()).with_rapid(&[true, false]);
+        let mut net = Net::new(vec![stp], &[]);
+        net.start(0);
+        assert!(net.bridges[0].forwarding(0));
+        assert!(!net.bridges[0].forwarding(1));
+        // edge ports forward without proposing
+        assert!(net.sent.iter().all(|(_, p, bpdu)| *p == 1 || !bpdu.proposal));
+    }
+
+    #[test]
+    fn alternate_port_takes_over_at_once() {
+        let mut net = Net::new(vec![rapid(1, 2), rapid(2, 2)], &[((0, 0), (1, 0)), ((0, 1), (1, 1))]);
+        net.start(0);
+        let stp = &net.bridges[1];
+        assert_eq!((stp.role(0), stp.state(0)), (PortRole::Root, PortState::Forwarding));
+        assert_eq!((stp.role(1), stp.state(1)), (PortRole::Alternate, PortState::Discarding));
+
+        net.cut(0);
+        net.on(1, 10, |stp, out| stp.link_down(0, 10, &mut |a| out(a)));
+        let stp = &net.bridges[1];
+        assert_eq!(stp.role(0), PortRole::Disabled);
+        assert_eq!((stp.role(1), stp.state(1)), (PortRole::Root, PortState::Forwarding));
+        assert_eq!(stp.root(), id(1));
+    }
+
+    #[test]
+    fn backup_port_on_segment_of_own_port() {
+        // both ports of the bridge on the same segment, hearing each other
+        let mut net = Net::new(vec![rapid(1, 2)], &[((0, 0), (0, 1))]);
+        net.start(0);
+        let stp = &net.bridges[0];
+        assert!(stp.is_root());
+        assert_eq!(stp.role(0), PortRole::Designated);
+        assert_eq!((stp.role(1), stp.state(1)), (PortRole::Backup, PortState::Discarding));
+    }
+
+    #[test]
+    fn falls_back_to_classic_neighbour() {
+        let timers = Timers::default();
+        // the classic bridge starts first, so the rapid one hears a classic BPDU before sending any
+        let mut net = Net::new(vec![Stp::new(id(2), &[19], timers), rapid(1, 1)], &[((0, 0), (1, 0))]);
+        net.start(0);
+        assert!(net.sent.iter().any(|(b, _, bpdu)| *b == 0 && bpdu.kind == BpduKind::Config));
+        net.run(0, 2 * timers.forward_delay);
+        // no agreement being possible, forwarding takes the forward delay twice
+        assert!(!net.bridges[1].forwarding(0));
+        net.run(2 * timers.forward_delay - STEP, settled(timers));
+        assert!(net.sent.iter().filter(|(b, _, _)| *b == 1).count() > 1);
+        assert!(net.sent.iter().filter(|(b, _, _)| *b == 1).all(|(_, _, bpdu)| {
+            bpdu.kind == BpduKind::Config && !bpdu.proposal
+        }));
+        assert_eq!(net.bridges[0].root(), id(1));
+        assert!(net.bridges[0].forwarding(0) && net.bridges[1].forwarding(0));
+    }
+}
@@ -0,0 +1,206 @@
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use crate::Frame;
+
+/// Bytes at the beginning of a frame file.
+pub const MAGIC: [u8; 8] = *b"NXFRAME1";
+
+/// Longest frame in MessagePack accepted by `FrameReader`, far above any frame written.
+pub const MAX_FRAME_LEN: usize = 64 * 1024;
+
+/// Writer of a length-delimited file of frames, one frame at a time.
+///
+/// The file is the magic bytes, the count of frames as a little-endian `u64`, then every frame as
+/// its length as a little-endian `u32` followed by the frame in MessagePack, as in `frame.rmp`. The
+/// count is written when the writer finishes, leaving it zero if it never does.
+pub struct FrameWriter<W: Write + Seek> {
+    writer: W,
+    count: u64,
+}
+
+impl<W: Write + Seek> FrameWriter<W> {
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&0u64.to_le_bytes())?;
+        Ok(FrameWriter { writer, count: 0 })
+    }
+
+    pub fn write(&mut self, frame: &Frame) -> io::Result<()> {
+        let bytes = rmp_serde::to_vec(frame).map_err(io::Error::other)?;
+        self.writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&bytes)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Count of frames written so far.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Write the count of frames in the header and return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.seek(SeekFrom::Start(MAGIC.len() as u64))?;
+        self.writer.write_all(&self.count.to_le_bytes())?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reader of a length-delimited file of frames written by `FrameWriter`, one frame at a time.
+pub struct FrameReader<R: Read> {
+    reader: R,
+    count: usize,
+    left: usize,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> FrameReader<R> {
+    /// Read the header of a frame file, failing if it is not one or if its writer never finished
+    /// it, leaving frames after a count of zero.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a frame file"));
+        }
+        let mut count = [0; 8];
+        reader.read_exact(&mut count)?;
+        let count = u64::from_le_bytes(count) as usize;
+        if count == 0 && reader.read(&mut [0])? > 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unfinished frame file"));
+        }
+        Ok(FrameReader { reader, count, left: count, buf: Vec::new() })
+    }
+
+    /// Count of frames in the file, read or not.
+    pub fn frame_cnt(&self) -> usize {
+        self.count
+    }
+
+    fn read_frame(&mut self) -> io::Result<Frame> {
+        let mut len = [0; 4];
+        self.reader.read_exact(&mut len)?;
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("frame of {} bytes", len)));
+        }
+        self.buf.resize(len, 0);
+        self.reader.read_exact(&mut self.buf)?;
+        rmp_serde::from_slice(&self.buf).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+impl<R: Read> Iterator for FrameReader<R> {
+    type Item = io::Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.left == 0 {
+            return None;
+        }
+        self.left -= 1;
+        Some(self.read_frame())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.left, Some(self.left))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use super::*;
+    use crate::{Address, Segment, VlanTag};
+
+    fn frames() -> Vec<Frame> {
+        (1..=3)
+            .map(|n| Frame {
+                src: Address { data: [0x02, 0, 0, 0, 0, n] },
+                src_seg: Segment { data: [0, n] },
+                dst: Address { data: [0x02, 0, 0, 0, 1, n] },
+                data: [n; 4],
+                vlan: if n == 2 { Some(VlanTag { vid: 10, pcp: 5 }) } else { None },
+            })
+            .collect()
+    }
+
+    fn write(frames: &[Frame], finish: bool) -> Vec<u8> {
+        let mut writer = FrameWriter::new(Cursor::new(Vec::new())).unwrap();
+        for frame in frames {
+            writer.write(frame).unwrap();
+        }
+        assert_eq!(writer.count(), frames.len() as u64);
+        if finish {
+            writer.finish().unwrap().into_inner()
+        } else {
+            writer.writer.into_inner()
+        }
+    }
+
+    /// Header of a finished file holding a count of frames.
+    fn header(count: u64) -> Vec<u8> {
+        [&MAGIC[..], &count.to_le_bytes()].concat()
+    }
+
+    fn error(bytes: Vec<u8>) -> io::Error {
+        FrameReader::new(Cursor::new(bytes)).err().expect("frame file accepted")
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = write(&frames(), true);
+        assert_eq!(bytes[..16], header(3));
+        let reader = FrameReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.frame_cnt(), 3);
+        assert_eq!(reader.size_hint(), (3, Some(3)));
+        assert_eq!(reader.collect::<io::Result<Vec<_>>>().unwrap(), frames());
+    }
+
+    #[test]
+    fn empty_file() {
+        let bytes = write(&[], true);
+        assert_eq!(bytes, header(0));
+        let mut reader = FrameReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.frame_cnt(), 0);
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn rejects_unfinished_file() {
+        let err = error(write(&frames(), false));
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "unfinished frame file");
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut bytes = write(&frames(), true);
+        bytes[7] = b'2';
+        let err = error(bytes);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "not a frame file");
+        assert_eq!(error(MAGIC[..4].to_vec()).kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn rejects_oversized_frame() {
+        let mut bytes = header(1);
+        bytes.extend_from_slice(&(MAX_FRAME_LEN as u32 + 1).to_le_bytes());
+        let mut reader = FrameReader::new(Cursor::new(bytes)).unwrap();
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), format!("frame of {} bytes", MAX_FRAME_LEN + 1));
+    }
+
+    #[test]
+    fn rejects_truncated_file() {
+        let mut bytes = write(&frames(), true);
+        bytes.truncate(bytes.len() - 1);
+        let results = FrameReader::new(Cursor::new(bytes)).unwrap().collect::<Vec<_>>();
+        assert_eq!(results.len(), 3);
+        assert!(results[..2].iter().all(|r| r.is_ok()));
+        assert_eq!(results[2].as_ref().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
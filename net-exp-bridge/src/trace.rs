@@ -0,0 +1,154 @@
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::{BufRead, Write};
+use crate::{Address, Frame, ParseError, Segment};
+
+/// Header written on top of text traces of frames.
+pub const FRAME_HEADER: &str = "# src src_seg dst data [vid/pcp]";
+
+/// Reason a text trace cannot be read.
+#[derive(Debug)]
+pub enum TraceError {
+    Io(io::Error),
+    /// Line not parsed, numbered from 1.
+    Parse { line: usize, err: ParseError },
+}
+
+impl Display for TraceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceError::Io(err) => write!(f, "{}", err),
+            TraceError::Parse { line, err } => write!(f, "line {}: {}", line, err),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+/// Read records of a line-oriented text trace one by one, parsing each line with a function.
+///
+/// Blank lines and lines starting with `#` are skipped, as is whitespace around lines.
+pub fn read_lines<T, R: BufRead>(reader: R, parse: impl Fn(&str) -> Result<T, ParseError>)
+                                 -> impl Iterator<Item = Result<T, TraceError>> {
+    reader.lines()
+        .enumerate()
+        .filter_map(move |(i, line)| {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => return Some(Err(TraceError::Io(err))),
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            Some(parse(line).map_err(|err| TraceError::Parse { line: i + 1, err }))
+        })
+}
+
+/// Read frames of a text trace one by one, in the order they are sent.
+///
+/// Every frame is a line as written by `Display` of `Frame`:
+///
+/// ```text
+/// # src src_seg dst data [vid/pcp]
+/// 02:00:00:00:00:01 00:01 02:00:00:00:00:02 0a0b0c0d
+/// 02:00:00:00:00:02 00:02 ff:ff:ff:ff:ff:ff 00000000 10/5
+/// ```
+///
+/// where `src` and `dst` are addresses (also `aa-bb-cc-dd-ee-ff` or `aabb.ccdd.eeff`), `src_seg`
+/// is the segment the frame comes from, `data` is four bytes in hexadecimal digits and the
+/// optional tag is the VLAN identifier and priority in decimal.
+pub fn read_frames<R: BufRead>(reader: R) -> impl Iterator<Item = Result<Frame, TraceError>> {
+    read_lines(reader, str::parse)
+}
+
+/// Write frames as a text trace, with a header.
+pub fn write_frames<'a, W: Write>(mut writer: W, frames: impl IntoIterator<Item = &'a Frame>) -> io::Result<()> {
+    writeln!(writer, "{}", FRAME_HEADER)?;
+    for frame in frames {
+        writeln!(writer, "{}", frame)?;
+    }
+    writer.flush()
+}
+
+/// Parse a line of the mapping of addresses to segments, i.e. `addr seg`.
+pub fn parse_location(line: &str) -> Result<(Address, Segment), ParseError> {
+    let (addr, seg) = line.split_once(' ').ok_or(ParseError::MissingField("seg"))?;
+    let pos = addr.len() + 1;
+    let addr = addr.parse()?;
+    let seg = seg.parse::<Segment>().map_err(|err| err.at(pos))?;
+    Ok((addr, seg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VlanTag;
+
+    fn frames() -> Vec<Frame> {
+        (1..=3)
+            .map(|n| Frame {
+                src: Address { data: [0x02, 0, 0, 0, 0, n] },
+                src_seg: Segment { data: [0, n] },
+                dst: if n == 3 { Address::BROADCAST } else { Address { data: [0x02, 0, 0, 0, 1, n] } },
+                data: [n, 0xab, 0xcd, 0xef],
+                vlan: if n == 2 { Some(VlanTag { vid: 10, pcp: 5 }) } else { None },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let mut text = Vec::new();
+        write_frames(&mut text, &frames()).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.starts_with(FRAME_HEADER));
+        assert_eq!(text.lines().count(), 4);
+        let read = read_frames(text.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(read, frames());
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let text = "# header\n\n  02:00:00:00:00:01 00:01 02:00:00:00:00:02 0a0b0c0d  \n\
+                    \t\n  # indented comment\n0200.0000.0002 00:02 ff-ff-ff-ff-ff-ff 00000000 10/5\n";
+        let read = read_frames(text.as_bytes()).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].to_string(), "02:00:00:00:00:01 00:01 02:00:00:00:00:02 0a0b0c0d");
+        assert_eq!(read[1].to_string(), "02:00:00:00:00:02 00:02 ff:ff:ff:ff:ff:ff 00000000 10/5");
+    }
+
+    #[test]
+    fn errors_name_their_line() {
+        let text = "# header\n02:00:00:00:00:01 00:01 02:00:00:00:00:02 0a0b0c0d\n\n\
+                    02:00:00:00:00:01 00:01 02:00:00:00:00:02 0a0b0c\n";
+        let read = read_frames(text.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(read.len(), 2);
+        assert!(read[0].is_ok());
+        let err = read[1].as_ref().unwrap_err();
+        assert!(matches!(err, TraceError::Parse { line: 4, err: ParseError::WrongLength { found: 6, expected: 8 } }));
+        assert_eq!(err.to_string(), "line 4: 6 found where 8 expected");
+    }
+
+    #[test]
+    fn reading_goes_on_after_errors() {
+        let text = "bad\n02:00:00:00:00:01 00:01 02:00:00:00:00:02 0a0b0c0d\n";
+        let read = read_frames(text.as_bytes()).collect::<Vec<_>>();
+        assert!(matches!(read[0], Err(TraceError::Parse { line: 1, .. })));
+        assert!(read[1].is_ok());
+    }
+
+    #[test]
+    fn locations() {
+        let addr = Address { data: [0x02, 0, 0, 0, 0, 1] };
+        let seg = Segment { data: [0x12, 0x34] };
+        assert_eq!(parse_location("02:00:00:00:00:01 12:34"), Ok((addr, seg)));
+        assert_eq!(parse_location("02:00:00:00:00:01"), Err(ParseError::MissingField("seg")));
+        assert_eq!(parse_location("02:00:00:00:0g:01 12:34"), Err(ParseError::BadHexDigit(13)));
+        assert_eq!(parse_location("02:00:00:00:00:01 12:3x"), Err(ParseError::BadHexDigit(22)));
+        let text = "# addr seg\n02:00:00:00:00:01 12:34\n02:00:00:00:00:01 12\n";
+        let read = read_lines(text.as_bytes(), parse_location).collect::<Vec<_>>();
+        assert_eq!(read[0].as_ref().unwrap(), &(addr, seg));
+        assert!(matches!(read[1], Err(TraceError::Parse { line: 3, err: ParseError::WrongLength { .. } })));
+    }
+}